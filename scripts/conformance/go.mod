@@ -0,0 +1,5 @@
+module github.com/marician-net/IBC-Portal-ADO/scripts/conformance
+
+go 1.21
+
+require github.com/cosmos/ibc-go/v7 v7.3.1
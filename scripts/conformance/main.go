@@ -0,0 +1,31 @@
+// Prints the ibc-go encodings the vectors in src/conformance.rs must match, one per line in
+// the order they are declared there. `go mod tidy` fetches the pinned ibc-go and writes go.sum,
+// then `go run .`
+package main
+
+import (
+	"errors"
+	"fmt"
+
+	transfertypes "github.com/cosmos/ibc-go/v7/modules/apps/transfer/types"
+	channeltypes "github.com/cosmos/ibc-go/v7/modules/core/04-channel/types"
+)
+
+func main() {
+	packets := []transfertypes.FungibleTokenPacketData{
+		// PACKET
+		transfertypes.NewFungibleTokenPacketData("transfer/channel-0/uatom", "1000000", "cosmos1sender", "juno1receiver", ""),
+		// PACKET_WITH_MEMO
+		transfertypes.NewFungibleTokenPacketData("ucosm", "42", "cosmos1sender", "juno1receiver", `{"forward":{"port":"transfer","channel":"channel-7"}}`),
+		// PACKET_LARGE_AMOUNT
+		transfertypes.NewFungibleTokenPacketData("uosmo", "340282366920938463463374607431768211455", "osmo1sender", "juno1receiver", ""),
+	}
+	for _, packet := range packets {
+		fmt.Println(string(packet.GetBytes()))
+	}
+
+	// ACK_SUCCESS
+	fmt.Println(string(channeltypes.NewResultAcknowledgement([]byte{1}).Acknowledgement()))
+	// ACK_ERROR, an error without a registered code is reported as ABCI code 1
+	fmt.Println(string(channeltypes.NewErrorAcknowledgement(errors.New("insufficient funds")).Acknowledgement()))
+}
@@ -0,0 +1,105 @@
+use crate::msg::AddressIssue;
+
+// bech32 as specified in BIP-173, which is what cosmos chains use for account addresses
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+const CHECKSUM_LEN: usize = 6;
+const MIN_LENGTH: usize = 8;
+const MAX_LENGTH: usize = 90;
+
+/// Checks a remote address without any knowledge of the remote chain beyond its prefix.
+/// An empty result means the address is valid.
+pub fn verify_remote_address(address: &str, expected_prefix: Option<&str>) -> Vec<AddressIssue> {
+    let mut issues = vec![];
+    if address.len() < MIN_LENGTH || address.len() > MAX_LENGTH {
+        issues.push(AddressIssue::InvalidLength {
+            length: address.len() as u64,
+        });
+    }
+    if address.chars().any(|c| c.is_ascii_lowercase())
+        && address.chars().any(|c| c.is_ascii_uppercase())
+    {
+        issues.push(AddressIssue::MixedCase {});
+    }
+
+    let address = address.to_lowercase();
+    let (prefix, data) = match address.rfind('1') {
+        Some(idx) if idx > 0 && address.len() - idx > CHECKSUM_LEN => {
+            (&address[..idx], &address[idx + 1..])
+        }
+        _ => {
+            issues.push(AddressIssue::NoSeparator {});
+            return issues;
+        }
+    };
+    if let Some(expected) = expected_prefix {
+        if prefix != expected {
+            issues.push(AddressIssue::WrongPrefix {
+                expected: expected.to_string(),
+                found: prefix.to_string(),
+            });
+        }
+    }
+    if let Some(c) = prefix.chars().find(|c| !('!'..='~').contains(c)) {
+        issues.push(AddressIssue::InvalidCharacter {
+            character: c.to_string(),
+        });
+        return issues;
+    }
+
+    let mut values = Vec::with_capacity(data.len());
+    for c in data.chars() {
+        match CHARSET.iter().position(|&b| b as char == c) {
+            Some(v) => values.push(v as u8),
+            None => {
+                issues.push(AddressIssue::InvalidCharacter {
+                    character: c.to_string(),
+                });
+                return issues;
+            }
+        }
+    }
+
+    let mut checked = expand_prefix(prefix);
+    checked.extend_from_slice(&values);
+    if polymod(&checked) != 1 {
+        issues.push(AddressIssue::InvalidChecksum {});
+    }
+
+    // account addresses are 20 bytes, contract and module addresses 32
+    let payload = values.len() - CHECKSUM_LEN;
+    let bytes = payload * 5 / 8;
+    if payload * 5 % 8 >= 5 || (bytes != 20 && bytes != 32) {
+        issues.push(AddressIssue::InvalidPayloadLength {
+            length: bytes as u64,
+        });
+    }
+    issues
+}
+
+fn expand_prefix(prefix: &str) -> Vec<u8> {
+    let mut expanded: Vec<u8> = prefix.bytes().map(|b| b >> 5).collect();
+    expanded.push(0);
+    expanded.extend(prefix.bytes().map(|b| b & 0x1f));
+    expanded
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+    for v in values {
+        let top = chk >> 25;
+        chk = (chk & 0x01ff_ffff) << 5 ^ u32::from(*v);
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::{Coin, Uint128};
+use cosmwasm_std::{Api, Coin, StdResult, Uint128};
 use cw20::Cw20Coin;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -42,3 +42,11 @@ impl Amount {
         }
     }
 }
+
+/// Validates the address of a "cw20:<address>" denom, native denoms are returned unchanged
+pub fn normalize_denom(api: &dyn Api, denom: String) -> StdResult<String> {
+    match denom.strip_prefix("cw20:") {
+        Some(address) => Ok(format!("cw20:{}", api.addr_validate(address)?)),
+        None => Ok(denom),
+    }
+}
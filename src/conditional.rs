@@ -0,0 +1,140 @@
+use crate::amount::Amount;
+use crate::contract::execute_transfer;
+use crate::error::ContractError;
+use crate::ibc::send_amount;
+use crate::msg::{ConditionResponse, ConditionalMsg, OracleQueryMsg, TransferMsg};
+use crate::schedule::validate_escrow;
+use crate::state::{
+    increase_escrowed, reduce_escrowed, ConditionalTransfer, CONDITIONALS, CONDITIONAL_COUNT,
+    ORACLES,
+};
+use cosmwasm_std::{attr, Addr, Deps, DepsMut, Env, Response, StdResult};
+use cw0::Expiration;
+
+pub fn execute_create_conditional(
+    deps: DepsMut,
+    env: Env,
+    msg: ConditionalMsg,
+    amount: Amount,
+    sender: Addr,
+) -> Result<Response, ContractError> {
+    let never = matches!(msg.expires, Expiration::Never {});
+    if amount.is_empty() || never || msg.expires.is_expired(&env.block) {
+        return Err(ContractError::InvalidConditional {});
+    }
+    if !ORACLES.has(deps.storage, &msg.oracle) {
+        return Err(ContractError::NoSuchOracle { name: msg.oracle });
+    }
+    let timeout = validate_escrow(
+        deps.as_ref(),
+        env.block.time,
+        &msg.channel,
+        &amount,
+        msg.timeout,
+        msg.timeout_duration.as_deref(),
+    )?;
+
+    let id = CONDITIONAL_COUNT
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        + 1;
+    CONDITIONAL_COUNT.save(deps.storage, &id)?;
+    let conditional = ConditionalTransfer {
+        id,
+        sender,
+        channel: msg.channel,
+        remote_address: msg.remote_address,
+        amount,
+        oracle: msg.oracle,
+        condition: msg.condition,
+        expires: msg.expires,
+        timeout,
+    };
+    CONDITIONALS.save(deps.storage, id.into(), &conditional)?;
+    increase_escrowed(deps.storage, &conditional.amount)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "create_conditional"),
+        attr("conditional_id", id.to_string()),
+        attr("sender", &conditional.sender),
+        attr("oracle", &conditional.oracle),
+        attr("expires", conditional.expires.to_string()),
+    ]))
+}
+
+/// Asks the oracle and sends the packet if the condition holds. Fails without changes otherwise so
+/// the trigger can be retried until expiry.
+pub fn execute_trigger_conditional(
+    deps: DepsMut,
+    env: Env,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let conditional = load_conditional(deps.as_ref(), id)?;
+    if conditional.expires.is_expired(&env.block) {
+        return Err(ContractError::ConditionalExpired {});
+    }
+    let oracle = ORACLES
+        .may_load(deps.storage, &conditional.oracle)?
+        .ok_or_else(|| ContractError::NoSuchOracle {
+            name: conditional.oracle.clone(),
+        })?;
+    let query = OracleQueryMsg::CheckCondition {
+        condition: conditional.condition.clone(),
+    };
+    let res: ConditionResponse = deps.querier.query_wasm_smart(oracle, &query)?;
+    if !res.met {
+        return Err(ContractError::ConditionNotMet {});
+    }
+
+    CONDITIONALS.remove(deps.storage, id.into());
+    reduce_escrowed(deps.storage, &conditional.amount)?;
+    let transfer = TransferMsg {
+        channel: conditional.channel,
+        remote_address: conditional.remote_address,
+        timeout: conditional.timeout,
+        ..Default::default()
+    };
+    let res = execute_transfer(
+        deps,
+        env,
+        transfer,
+        conditional.amount,
+        conditional.sender,
+        None,
+    )?;
+    Ok(res.add_attribute("conditional_id", id.to_string()))
+}
+
+pub fn execute_reclaim_conditional(
+    deps: DepsMut,
+    env: Env,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let conditional = load_conditional(deps.as_ref(), id)?;
+    if !conditional.expires.is_expired(&env.block) {
+        return Err(ContractError::ConditionalNotExpired {});
+    }
+    CONDITIONALS.remove(deps.storage, id.into());
+    reduce_escrowed(deps.storage, &conditional.amount)?;
+
+    Ok(Response::new()
+        .add_message(send_amount(
+            conditional.amount,
+            conditional.sender.to_string(),
+        ))
+        .add_attributes(vec![
+            attr("action", "reclaim_conditional"),
+            attr("conditional_id", id.to_string()),
+            attr("sender", conditional.sender),
+        ]))
+}
+
+fn load_conditional(deps: Deps, id: u64) -> Result<ConditionalTransfer, ContractError> {
+    CONDITIONALS
+        .may_load(deps.storage, id.into())?
+        .ok_or(ContractError::NoSuchConditional { id })
+}
+
+pub fn query_conditional(deps: Deps, id: u64) -> StdResult<ConditionalTransfer> {
+    CONDITIONALS.load(deps.storage, id.into())
+}
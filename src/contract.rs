@@ -1,76 +1,1063 @@
-use crate::amount::Amount;
+use crate::address::verify_remote_address;
+use crate::amount::{normalize_denom, Amount};
+use crate::conditional::{
+    execute_create_conditional, execute_reclaim_conditional, execute_trigger_conditional,
+    query_conditional,
+};
+use crate::deposit::{
+    execute_cancel_deposit, execute_deposit, execute_release, execute_set_deposit_operator,
+    query_deposit, query_deposits_by_sender,
+};
 use crate::error::ContractError;
-use crate::ibc::Ics20Packet;
+use crate::fees::{
+    accrue_fee, collected_fees, credit_referrer, protocol_fee, referrer_fees, split_fee,
+    take_fee_coin, validate_fee_config, validate_fee_tiers,
+};
+use crate::htlc::{
+    execute_claim_htlc, execute_lock_htlc, execute_refund_htlc, query_htlc, query_htlcs_by_hash,
+};
+use crate::ibc::{route_memo, send_amount, Ics20Packet, EVENT_TRANSFER, NATIVE_TRANSFER_ID};
 use crate::msg::{
-    ChannelResponse, ConfigResponse, ExecuteMsg, InitMsg, ListChannelsResponse, PortResponse,
-    QueryMsg, TransferMsg, WhitelistResponse,
+    AckFormatResponse, AddressIssue, ChannelAllowlistResponse, ChannelModeResponse,
+    ChannelResponse, CollectedFeesResponse, ConfigHistoryResponse, ConfigResponse,
+    DeadLetterResolution, DeadLettersResponse, DenomAlias, DenomAliasesResponse, ExecuteMsg,
+    FeeConfigMsg, FeeConfigResponse, FeeTiersResponse, InitMsg, ListChannelsResponse,
+    ListTokenMetadataResponse, MetricsResponse, MigrateMsg, NativeAllowlistResponse,
+    NativeTransferResolution, NativeTransfersResponse, OracleInfo, OraclesResponse, ParamInfo,
+    ParamsResponse, PauseResponse, PortResponse, QueryMsg, ReceiveMsg, ReferralFeesResponse,
+    ResolveDenomResponse, RouteInfo, RoutesResponse, TokenMetadataResponse, TransferMsg,
+    TransfersResponse, TreasuryLogResponse, VerifyRemoteAddressResponse, WhitelistResponse,
+    WhitelistVerifierMsg, WhitelistVerifierResponse, WhitelistedBatchItem,
+    WhitelistedBatchResponse,
+};
+use crate::pull::{cw20_balance, execute_transfer_cw20};
+use crate::schedule::{
+    execute_cancel_scheduled, execute_run_scheduled, execute_schedule_transfer,
+    execute_update_scheduled, query_schedule, query_schedules_by_sender,
 };
 use crate::state::{
-    increase_channel_balance, Config, CHANNEL_INFO, CHANNEL_STATE, CONFIG, WHITE_LIST,
+    active_pause, increase_channel_balance, increase_escrowed, is_known_channel,
+    native_denom_allowed, param_addr, param_u64, record_config_change, record_token_use,
+    record_treasury_transfer, reduce_escrowed, resolve_denom, save_transfer_record,
+    total_outstanding, update_metrics, AckFormat, ChannelMode, Config, ConfigChange, FeeCollector,
+    FeeConfig, FeeTier, Metrics, NativeTransfer, ParamValue, PauseInfo, PauseSource, Route,
+    TokenMetadata, WhitelistVerifier, CHANNEL_ALLOWLIST, CHANNEL_CONFIG, CHANNEL_INFO,
+    CHANNEL_STATE, COLLECTED_FEES, CONFIG, CONFIG_HISTORY, DEAD_LETTERS,
+    DEFAULT_GUARDIAN_PAUSE_SECONDS, DEFAULT_MAX_MEMO_LENGTH, DEFAULT_MAX_TIMEOUT, DENOM_ALIASES,
+    ESCROWED, FEE_CONFIG, FEE_ON_TRANSFER, FEE_TIERS, LEGACY_CONFIG, LIFETIME_FEES, METRICS,
+    NATIVE_ALLOWLIST, NATIVE_ALLOWLIST_ENABLED, NATIVE_AWAITING_SEQUENCE, NATIVE_TRANSFERS,
+    NATIVE_TRANSFER_COUNT, ORACLES, PARAMS, PARAM_GUARDIAN, PARAM_GUARDIAN_PAUSE_BLOCKS,
+    PARAM_GUARDIAN_PAUSE_SECONDS, PARAM_MAX_MEMO_LENGTH, PARAM_MAX_PACKET_SIZE, PARAM_MAX_TIMEOUT,
+    PAUSE, REFERRER_FEES, ROUTES, TOKEN_METADATA, TOKEN_STATS, TRANSFERS, TRANSFERS_BY_RECEIVER,
+    TREASURY_LOG, VERIFIER_APPROVALS, WHITELIST_VERIFIER, WHITE_LIST,
 };
+use crate::stream::{
+    execute_cancel_stream, execute_create_stream, execute_pause_stream, execute_resume_stream,
+    execute_tick_streams, query_stream,
+};
+use crate::verifier::check_cw20;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, from_binary, to_binary, Addr, Binary, Deps, DepsMut, Env, IbcMsg, IbcQuery, MessageInfo,
-    Order, PortIdResponse, Response, StdResult,
+    attr, from_binary, to_binary, Addr, Binary, Coin, Deps, DepsMut, Env, Event, IbcMsg, IbcQuery,
+    MessageInfo, Order, PortIdResponse, Response, StdError, StdResult, Storage, SubMsg, Timestamp,
 };
-use cw0::PaymentError;
+use cw0::{one_coin, Expiration, PaymentError};
 use cw2::set_contract_version;
-use cw20::{Cw20Coin, Cw20ReceiveMsg};
+use cw20::{
+    Cw20Coin, Cw20QueryMsg, Cw20ReceiveMsg, LogoInfo, MarketingInfoResponse, TokenInfoResponse,
+};
+use cw_storage_plus::Bound;
+use std::convert::TryInto;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "andromeda-potal-ado";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// settings for pagination
+pub(crate) const MAX_LIMIT: u32 = 30;
+pub(crate) const DEFAULT_LIMIT: u32 = 10;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
+    env: Env,
+    info: MessageInfo,
     msg: InitMsg,
 ) -> StdResult<Response> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     let cfg = Config {
         default_timeout: msg.default_timeout,
+        owner: info.sender.clone(),
+    };
+    CONFIG.save(deps.storage, &cfg)?;
+    METRICS.save(
+        deps.storage,
+        &Metrics {
+            instantiate_height: env.block.height,
+            ..Default::default()
+        },
+    )?;
+    // the initial settings are the first version in the history
+    record_config_change(
+        deps.storage,
+        ConfigChange::Config(cfg),
+        info.sender,
+        env.block.height,
+    )?;
+
+    for white_addr in msg.whitelist {
+        let contract = deps.api.addr_validate(&white_addr)?;
+        WHITE_LIST.save(deps.storage, &contract, &true)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "instantiate"),
+        attr("default_timeout", msg.default_timeout.to_string()),
+    ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let owner = msg
+        .owner
+        .map(|owner| deps.api.addr_validate(&owner))
+        .transpose()?;
+    // configs from before the owner was added fail to load as Config
+    let cfg = match CONFIG.load(deps.storage) {
+        Ok(cfg) => Config {
+            owner: owner.unwrap_or(cfg.owner),
+            ..cfg
+        },
+        Err(_) => Config {
+            default_timeout: LEGACY_CONFIG.load(deps.storage)?.default_timeout,
+            owner: owner.ok_or(ContractError::MissingOwner {})?,
+        },
+    };
+    CONFIG.save(deps.storage, &cfg)?;
+    let version = record_config_change(
+        deps.storage,
+        ConfigChange::Config(cfg.clone()),
+        env.contract.address,
+        env.block.height,
+    )?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("owner", cfg.owner),
+        attr("version", version.to_string()),
+    ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+        ExecuteMsg::Transfer(msg) => {
+            let mut funds = info.funds;
+            // a flat fee in a native fee denom comes attached as a second coin
+            let fee_coin = if msg.fee_in_fee_denom {
+                take_fee_coin(deps.storage, &mut funds)?
+            } else {
+                None
+            };
+            let coin = match funds.len() {
+                0 => Err(PaymentError::NoFunds {}),
+                1 => {
+                    let coin = &funds[0];
+                    if coin.amount.is_zero() {
+                        Err(PaymentError::NoFunds {})
+                    } else {
+                        Ok(coin.clone())
+                    }
+                }
+                _ => Err(PaymentError::MultipleDenoms {}),
+            }?;
+            execute_transfer(deps, env, msg, Amount::Native(coin), info.sender, fee_coin)
+        }
+        ExecuteMsg::TransferCw20 {
+            contract,
+            amount,
+            msg,
+        } => execute_transfer_cw20(deps, env, info, contract, amount, msg),
+        ExecuteMsg::ScheduleTransfer(msg) => {
+            let coin = one_coin(&info)?;
+            execute_schedule_transfer(deps, env, msg, Amount::Native(coin), info.sender)
+        }
+        ExecuteMsg::RunScheduled { limit } => execute_run_scheduled(deps, env, limit),
+        ExecuteMsg::CreateStream(msg) => {
+            let coin = one_coin(&info)?;
+            execute_create_stream(deps, env, msg, Amount::Native(coin), info.sender)
+        }
+        ExecuteMsg::TickStreams { start_after, limit } => {
+            execute_tick_streams(deps, env, start_after, limit)
+        }
+        ExecuteMsg::PauseStream { id } => execute_pause_stream(deps, env, info, id),
+        ExecuteMsg::ResumeStream { id } => execute_resume_stream(deps, env, info, id),
+        ExecuteMsg::CancelStream { id } => execute_cancel_stream(deps, env, info, id),
+        ExecuteMsg::CreateConditional(msg) => {
+            let coin = one_coin(&info)?;
+            execute_create_conditional(deps, env, msg, Amount::Native(coin), info.sender)
+        }
+        ExecuteMsg::TriggerConditional { id } => execute_trigger_conditional(deps, env, id),
+        ExecuteMsg::ReclaimConditional { id } => execute_reclaim_conditional(deps, env, id),
+        ExecuteMsg::Deposit { operator } => {
+            let coin = one_coin(&info)?;
+            execute_deposit(deps, env, Amount::Native(coin), info.sender, operator)
+        }
+        ExecuteMsg::SetDepositOperator { id, operator } => {
+            execute_set_deposit_operator(deps, info, id, operator)
+        }
+        ExecuteMsg::Release { id, msg } => {
+            let mut funds = info.funds.clone();
+            let fee_coin = if msg.fee_in_fee_denom {
+                take_fee_coin(deps.storage, &mut funds)?
+            } else {
+                None
+            };
+            // the deposit is what gets sent, anything else attached would be stranded
+            if !funds.is_empty() {
+                return Err(PaymentError::NonPayable {}.into());
+            }
+            execute_release(deps, env, info, id, msg, fee_coin)
+        }
+        ExecuteMsg::CancelDeposit { id } => execute_cancel_deposit(deps, info, id),
+        ExecuteMsg::RegisterOracle { name, address } => {
+            execute_register_oracle(deps, info, name, address)
+        }
+        ExecuteMsg::RemoveOracle { name } => execute_remove_oracle(deps, info, name),
+        ExecuteMsg::SetRoute { name, route } => execute_set_route(deps, info, name, route),
+        ExecuteMsg::RemoveRoute { name } => execute_remove_route(deps, info, name),
+        ExecuteMsg::LockHtlc(msg) => {
+            let coin = one_coin(&info)?;
+            execute_lock_htlc(deps, env, msg, Amount::Native(coin), info.sender)
+        }
+        ExecuteMsg::ClaimHtlc { preimage, id } => execute_claim_htlc(deps, env, preimage, id),
+        ExecuteMsg::RefundHtlc { id } => execute_refund_htlc(deps, env, id),
+        ExecuteMsg::CancelScheduled { id } => execute_cancel_scheduled(deps, info, id),
+        ExecuteMsg::UpdateScheduled {
+            id,
+            remote_address,
+            amount,
+            interval,
+            end,
+            timeout,
+            timeout_duration,
+        } => execute_update_scheduled(
+            deps,
+            info,
+            id,
+            remote_address,
+            amount,
+            interval,
+            end,
+            timeout,
+            timeout_duration,
+        ),
+        ExecuteMsg::SetChannelMode { channel, mode } => {
+            execute_set_channel_mode(deps, info, channel, mode)
+        }
+        ExecuteMsg::SetAckFormat { channel, format } => {
+            execute_set_ack_format(deps, info, channel, format)
+        }
+        ExecuteMsg::SetRemotePrefix { channel, prefix } => {
+            execute_set_remote_prefix(deps, info, channel, prefix)
+        }
+        ExecuteMsg::UpdateConfig {
+            default_timeout,
+            owner,
+        } => execute_update_config(deps, env, info, default_timeout, owner),
+        ExecuteMsg::SetParam { key, value } => execute_set_param(deps, env, info, key, value),
+        ExecuteMsg::UnsetParam { key } => execute_unset_param(deps, env, info, key),
+        ExecuteMsg::Pause {} => execute_pause(deps, env, info),
+        ExecuteMsg::Unpause {} => execute_unpause(deps, info),
+        ExecuteMsg::ResolveDeadLetter { id, resolution } => {
+            execute_resolve_dead_letter(deps, info, id, resolution)
+        }
+        ExecuteMsg::SetDenomAlias {
+            channel,
+            alias,
+            denom,
+        } => execute_set_denom_alias(deps, info, channel, alias, denom),
+        ExecuteMsg::RemoveDenomAlias { channel, alias } => {
+            execute_remove_denom_alias(deps, info, channel, alias)
+        }
+        ExecuteMsg::SetFeeConfig { fee_config } => execute_set_fee_config(deps, info, fee_config),
+        ExecuteMsg::DistributeFees {} => execute_distribute_fees(deps),
+        ExecuteMsg::TreasuryTransfer {
+            amount,
+            channel,
+            remote_address,
+        } => execute_treasury_transfer(deps, env, info, amount, channel, remote_address),
+        ExecuteMsg::SetFeeTiers { denom, tiers } => execute_set_fee_tiers(deps, info, denom, tiers),
+        ExecuteMsg::ClaimReferralFees {} => execute_claim_referral_fees(deps, info),
+        ExecuteMsg::SetTokenMetadata { denom, metadata } => {
+            execute_set_token_metadata(deps, info, denom, metadata)
+        }
+        ExecuteMsg::RemoveTokenMetadata { denom } => {
+            execute_remove_token_metadata(deps, info, denom)
+        }
+        ExecuteMsg::SyncTokenMetadata { contract } => execute_sync_token_metadata(deps, contract),
+        ExecuteMsg::SetWhitelistVerifier { verifier } => {
+            execute_set_whitelist_verifier(deps, info, verifier)
+        }
+        ExecuteMsg::SetFeeOnTransfer { contract, enabled } => {
+            execute_set_fee_on_transfer(deps, info, contract, enabled)
+        }
+        ExecuteMsg::SetChannelAllowlist { channel, enabled } => {
+            execute_set_channel_allowlist(deps, info, channel, enabled)
+        }
+        ExecuteMsg::UpdateChannelAllowlist {
+            channel,
+            add,
+            remove,
+        } => execute_update_channel_allowlist(deps, info, channel, add, remove),
+        ExecuteMsg::SetNativeAllowlist { enabled } => {
+            execute_set_native_allowlist(deps, info, enabled)
+        }
+        ExecuteMsg::UpdateNativeAllowlist { add, remove } => {
+            execute_update_native_allowlist(deps, info, add, remove)
+        }
+        ExecuteMsg::ResolveNativeTransfer { id, resolution } => {
+            execute_resolve_native_transfer(deps, env, info, id, resolution)
+        }
+    }
+}
+
+fn ensure_owner(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if &cfg.owner != sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+pub fn execute_set_channel_mode(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel: String,
+    mode: ChannelMode,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    // native transfer channels belong to the transfer port, so they are never in CHANNEL_INFO
+    let portal_channel = CHANNEL_INFO.has(deps.storage, &channel);
+    if mode == ChannelMode::Portal && !portal_channel {
+        return Err(ContractError::NoSuchChannel { id: channel });
+    }
+    // packets on our own channels would never reach the transfer module
+    if mode == ChannelMode::NativeTransfer && portal_channel {
+        return Err(ContractError::PortalChannel { id: channel });
+    }
+    CHANNEL_CONFIG.update(deps.storage, &channel, |orig| -> StdResult<_> {
+        let mut cfg = orig.unwrap_or_default();
+        cfg.mode = mode;
+        Ok(cfg)
+    })?;
+
+    let mode = match mode {
+        ChannelMode::Portal => "portal",
+        ChannelMode::NativeTransfer => "native_transfer",
+    };
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_channel_mode"),
+        attr("channel", channel),
+        attr("mode", mode),
+    ]))
+}
+
+pub fn execute_set_remote_prefix(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel: String,
+    prefix: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    if !is_known_channel(deps.storage, &channel)? {
+        return Err(ContractError::NoSuchChannel { id: channel });
+    }
+    CHANNEL_CONFIG.update(deps.storage, &channel, |orig| -> StdResult<_> {
+        let mut cfg = orig.unwrap_or_default();
+        cfg.remote_prefix = prefix.clone();
+        Ok(cfg)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_remote_prefix"),
+        attr("channel", channel),
+        attr("prefix", prefix.unwrap_or_default()),
+    ]))
+}
+
+pub fn execute_set_ack_format(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel: String,
+    format: AckFormat,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    if !is_known_channel(deps.storage, &channel)? {
+        return Err(ContractError::NoSuchChannel { id: channel });
+    }
+    CHANNEL_CONFIG.update(deps.storage, &channel, |orig| -> StdResult<_> {
+        let mut cfg = orig.unwrap_or_default();
+        cfg.ack_format = format.clone();
+        Ok(cfg)
+    })?;
+
+    let format = match format {
+        AckFormat::Ics20Json => "ics20_json",
+        AckFormat::Protobuf => "protobuf",
+        AckFormat::Exact { .. } => "exact",
+    };
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_ack_format"),
+        attr("channel", channel),
+        attr("format", format),
+    ]))
+}
+
+pub fn execute_set_channel_allowlist(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel: String,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    if !is_known_channel(deps.storage, &channel)? {
+        return Err(ContractError::NoSuchChannel { id: channel });
+    }
+    CHANNEL_CONFIG.update(deps.storage, &channel, |orig| -> StdResult<_> {
+        let mut cfg = orig.unwrap_or_default();
+        cfg.token_allowlist = enabled;
+        Ok(cfg)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_channel_allowlist"),
+        attr("channel", channel),
+        attr("enabled", enabled.to_string()),
+    ]))
+}
+
+pub fn execute_update_channel_allowlist(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel: String,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    if !is_known_channel(deps.storage, &channel)? {
+        return Err(ContractError::NoSuchChannel { id: channel });
+    }
+    for denom in add {
+        let denom = normalize_denom(deps.api, denom)?;
+        CHANNEL_ALLOWLIST.save(deps.storage, (&channel, &denom), &true)?;
+    }
+    for denom in remove {
+        let denom = normalize_denom(deps.api, denom)?;
+        CHANNEL_ALLOWLIST.remove(deps.storage, (&channel, &denom));
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_channel_allowlist"),
+        attr("channel", channel),
+    ]))
+}
+
+pub fn execute_set_native_allowlist(
+    deps: DepsMut,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    NATIVE_ALLOWLIST_ENABLED.save(deps.storage, &enabled)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_native_allowlist"),
+        attr("enabled", enabled.to_string()),
+    ]))
+}
+
+pub fn execute_update_native_allowlist(
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    for denom in add {
+        NATIVE_ALLOWLIST.save(deps.storage, &denom, &true)?;
+    }
+    for denom in remove {
+        NATIVE_ALLOWLIST.remove(deps.storage, &denom);
+    }
+
+    Ok(Response::new().add_attribute("action", "update_native_allowlist"))
+}
+
+pub fn execute_update_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    default_timeout: Option<u64>,
+    owner: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    let mut cfg = CONFIG.load(deps.storage)?;
+    if let Some(default_timeout) = default_timeout {
+        cfg.default_timeout = default_timeout;
+    }
+    if let Some(owner) = owner {
+        cfg.owner = deps.api.addr_validate(&owner)?;
+    }
+    CONFIG.save(deps.storage, &cfg)?;
+    let version = record_config_change(
+        deps.storage,
+        ConfigChange::Config(cfg),
+        info.sender,
+        env.block.height,
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_config"),
+        attr("version", version.to_string()),
+    ]))
+}
+
+pub fn execute_set_param(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    key: String,
+    value: ParamValue,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    if key.is_empty() {
+        return Err(ContractError::EmptyParamKey {});
+    }
+    let value = match value {
+        ParamValue::Addr(addr) => ParamValue::Addr(deps.api.addr_validate(addr.as_str())?),
+        other => other,
+    };
+    PARAMS.save(deps.storage, &key, &value)?;
+    let change = ConfigChange::Param {
+        key: key.clone(),
+        value: Some(value),
+    };
+    record_config_change(deps.storage, change, info.sender, env.block.height)?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "set_param"), attr("key", key)]))
+}
+
+pub fn execute_unset_param(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    key: String,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    PARAMS.remove(deps.storage, &key);
+    let change = ConfigChange::Param {
+        key: key.clone(),
+        value: None,
+    };
+    record_config_change(deps.storage, change, info.sender, env.block.height)?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "unset_param"), attr("key", key)]))
+}
+
+pub fn execute_register_oracle(
+    deps: DepsMut,
+    info: MessageInfo,
+    name: String,
+    address: String,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    let address = deps.api.addr_validate(&address)?;
+    ORACLES.save(deps.storage, &name, &address)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "register_oracle"),
+        attr("name", name),
+        attr("address", address),
+    ]))
+}
+
+pub fn execute_remove_oracle(
+    deps: DepsMut,
+    info: MessageInfo,
+    name: String,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    ORACLES.remove(deps.storage, &name);
+
+    Ok(Response::new().add_attributes(vec![attr("action", "remove_oracle"), attr("name", name)]))
+}
+
+pub fn execute_set_route(
+    deps: DepsMut,
+    info: MessageInfo,
+    name: String,
+    route: Route,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    if route.hops.is_empty()
+        || route.hops.iter().any(|h| h.channel.is_empty())
+        || !is_known_channel(deps.storage, &route.channel)?
+    {
+        return Err(ContractError::InvalidRoute {});
+    }
+    ROUTES.save(deps.storage, &name, &route)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_route"),
+        attr("name", name),
+        attr("channel", route.channel),
+        attr("hops", route.hops.len().to_string()),
+    ]))
+}
+
+pub fn execute_remove_route(
+    deps: DepsMut,
+    info: MessageInfo,
+    name: String,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    ROUTES.remove(deps.storage, &name);
+
+    Ok(Response::new().add_attributes(vec![attr("action", "remove_route"), attr("name", name)]))
+}
+
+pub fn execute_pause(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let pause = if info.sender == cfg.owner {
+        PauseInfo {
+            source: PauseSource::Owner,
+            expires: Expiration::Never {},
+        }
+    } else if param_addr(deps.storage, PARAM_GUARDIAN)?.as_ref() == Some(&info.sender) {
+        // never let a guardian pause shorten or replace an owner pause
+        if let Some(current) = active_pause(deps.storage, &env.block)? {
+            if current.source == PauseSource::Owner {
+                return Err(ContractError::Paused {});
+            }
+        }
+        PauseInfo {
+            source: PauseSource::Guardian,
+            expires: guardian_pause_expiration(deps.as_ref(), &env)?,
+        }
+    } else {
+        return Err(ContractError::Unauthorized {});
+    };
+    PAUSE.save(deps.storage, &pause)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "pause"),
+        attr("sender", info.sender),
+        attr("expires", pause.expires.to_string()),
+    ]))
+}
+
+// The time based duration wins if both are set
+fn guardian_pause_expiration(deps: Deps, env: &Env) -> StdResult<Expiration> {
+    if let Some(seconds) = param_u64(deps.storage, PARAM_GUARDIAN_PAUSE_SECONDS)? {
+        // the param is owner-set, a huge value means a pause that practically never lifts
+        let nanos = seconds.saturating_mul(1_000_000_000);
+        return Ok(Expiration::AtTime(Timestamp::from_nanos(
+            env.block.time.nanos().saturating_add(nanos),
+        )));
+    }
+    if let Some(blocks) = param_u64(deps.storage, PARAM_GUARDIAN_PAUSE_BLOCKS)? {
+        return Ok(Expiration::AtHeight(
+            env.block.height.saturating_add(blocks),
+        ));
+    }
+    Ok(Expiration::AtTime(
+        env.block.time.plus_seconds(DEFAULT_GUARDIAN_PAUSE_SECONDS),
+    ))
+}
+
+pub fn execute_unpause(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    PAUSE.remove(deps.storage);
+
+    Ok(Response::new().add_attribute("action", "unpause"))
+}
+
+pub fn execute_resolve_dead_letter(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+    resolution: DeadLetterResolution,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    let letter = DEAD_LETTERS
+        .may_load(deps.storage, id.into())?
+        .ok_or(ContractError::NoSuchDeadLetter { id })?;
+    DEAD_LETTERS.remove(deps.storage, id.into());
+    reduce_escrowed(deps.storage, &letter.amount)?;
+
+    let res = Response::new().add_attributes(vec![
+        attr("action", "resolve_dead_letter"),
+        attr("dead_letter_id", id.to_string()),
+    ]);
+    let res = match resolution {
+        DeadLetterResolution::Redirect { recipient } => {
+            let recipient = deps.api.addr_validate(&recipient)?;
+            res.add_attribute("resolution", "redirect")
+                .add_attribute("recipient", &recipient)
+                .add_message(send_amount(letter.amount, recipient.into()))
+        }
+        DeadLetterResolution::WriteOff {} => res.add_attribute("resolution", "write_off"),
+    };
+    Ok(res)
+}
+
+pub fn execute_set_denom_alias(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel: String,
+    alias: String,
+    denom: String,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    if !is_known_channel(deps.storage, &channel)? {
+        return Err(ContractError::NoSuchChannel { id: channel });
+    }
+    let denom = normalize_denom(deps.api, denom)?;
+    DENOM_ALIASES.save(deps.storage, (&channel, &alias), &denom)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_denom_alias"),
+        attr("channel", channel),
+        attr("alias", alias),
+        attr("denom", denom),
+    ]))
+}
+
+pub fn execute_remove_denom_alias(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel: String,
+    alias: String,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    DENOM_ALIASES.remove(deps.storage, (&channel, &alias));
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "remove_denom_alias"),
+        attr("channel", channel),
+        attr("alias", alias),
+    ]))
+}
+
+pub fn execute_set_fee_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    fee_config: Option<FeeConfigMsg>,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    let fee_config = match fee_config {
+        Some(msg) => msg,
+        None => {
+            // without a config nobody could distribute what was collected, so pay it out now
+            let mut res = Response::new().add_attribute("action", "set_fee_config");
+            if let Some(cfg) = FEE_CONFIG.may_load(deps.storage)? {
+                res = distribute_collected(deps.storage, &cfg, res)?;
+                FEE_CONFIG.remove(deps.storage);
+            }
+            return Ok(res);
+        }
+    };
+
+    let mut fee_denom = fee_config.fee_denom;
+    if let Some(flat) = fee_denom.as_mut() {
+        flat.denom = normalize_denom(deps.api, flat.denom.clone())?;
+    }
+    let cfg = FeeConfig {
+        bps: fee_config.bps,
+        collector: deps.api.addr_validate(&fee_config.collector)?,
+        fee_denom,
+        referrer_bps: fee_config.referrer_bps,
+        collectors: fee_config
+            .collectors
+            .into_iter()
+            .map(|c| -> StdResult<_> {
+                Ok(FeeCollector {
+                    address: deps.api.addr_validate(&c.address)?,
+                    weight: c.weight,
+                })
+            })
+            .collect::<StdResult<_>>()?,
+    };
+    validate_fee_config(&cfg)?;
+    FEE_CONFIG.save(deps.storage, &cfg)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_fee_config"),
+        attr("bps", cfg.bps.to_string()),
+        attr("collector", cfg.collector),
+    ]))
+}
+
+pub fn execute_set_fee_tiers(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+    tiers: Vec<FeeTier>,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    let denom = normalize_denom(deps.api, denom)?;
+    validate_fee_tiers(&tiers)?;
+    if tiers.is_empty() {
+        FEE_TIERS.remove(deps.storage, &denom);
+    } else {
+        FEE_TIERS.save(deps.storage, &denom, &tiers)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_fee_tiers"),
+        attr("denom", denom),
+        attr("tiers", tiers.len().to_string()),
+    ]))
+}
+
+pub fn execute_distribute_fees(deps: DepsMut) -> Result<Response, ContractError> {
+    let cfg = FEE_CONFIG
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoFeeConfig {})?;
+    let res = Response::new().add_attribute("action", "distribute_fees");
+    Ok(distribute_collected(deps.storage, &cfg, res)?)
+}
+
+// pays every collected fee out to the collectors of the config
+fn distribute_collected(
+    storage: &mut dyn Storage,
+    cfg: &FeeConfig,
+    mut res: Response,
+) -> StdResult<Response> {
+    for fee in collected_fees(storage)? {
+        COLLECTED_FEES.remove(storage, &fee.denom());
+        if fee.is_empty() {
+            continue;
+        }
+        res = res.add_attribute("fee", format!("{}{}", fee.amount(), fee.denom()));
+        for (collector, share) in split_fee(cfg, fee.amount()) {
+            if !share.is_zero() {
+                let share = Amount::from_parts(fee.denom(), share);
+                res = res.add_message(send_amount(share, collector.into()));
+            }
+        }
+    }
+    Ok(res)
+}
+
+pub fn execute_treasury_transfer(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Amount,
+    channel: String,
+    remote_address: String,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    let contract = env.contract.address.clone();
+    let balance = match &amount {
+        Amount::Native(coin) => deps.querier.query_balance(&contract, &coin.denom)?.amount,
+        Amount::Cw20(coin) => {
+            let addr = deps.api.addr_validate(&coin.address)?;
+            cw20_balance(deps.as_ref(), &addr, &contract)?
+        }
+    };
+    let denom = amount.denom();
+    let locked = total_outstanding(deps.storage, &denom)?
+        + COLLECTED_FEES
+            .may_load(deps.storage, &denom)?
+            .unwrap_or_default()
+        + ESCROWED.may_load(deps.storage, &denom)?.unwrap_or_default();
+    let available = balance.saturating_sub(locked);
+    if amount.amount() > available {
+        return Err(ContractError::InsufficientTreasury { available });
+    }
+
+    let msg = TransferMsg {
+        channel: channel.clone(),
+        remote_address: remote_address.clone(),
+        ..TransferMsg::default()
+    };
+    let res = execute_transfer(
+        deps.branch(),
+        env.clone(),
+        msg,
+        amount.clone(),
+        contract,
+        None,
+    )?;
+    let id = record_treasury_transfer(
+        deps.storage,
+        amount,
+        channel,
+        remote_address,
+        info.sender,
+        env.block.height,
+    )?;
+    Ok(res
+        .add_attribute("treasury_transfer", "true")
+        .add_attribute("treasury_entry", id.to_string()))
+}
+
+pub fn execute_set_whitelist_verifier(
+    deps: DepsMut,
+    info: MessageInfo,
+    verifier: Option<WhitelistVerifierMsg>,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    let verifier = match verifier {
+        Some(msg) => msg,
+        None => {
+            WHITELIST_VERIFIER.remove(deps.storage);
+            return Ok(Response::new().add_attribute("action", "set_whitelist_verifier"));
+        }
     };
-    CONFIG.save(deps.storage, &cfg)?;
+    let verifier = WhitelistVerifier {
+        address: deps.api.addr_validate(&verifier.address)?,
+        cache_ttl: verifier.cache_ttl,
+    };
+    WHITELIST_VERIFIER.save(deps.storage, &verifier)?;
 
-    for white_addr in msg.whitelist {
-        let contract = deps.api.addr_validate(&white_addr)?;
-        WHITE_LIST.save(deps.storage, &contract, &true)?;
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_whitelist_verifier"),
+        attr("verifier", verifier.address),
+        attr(
+            "cache_ttl",
+            verifier.cache_ttl.unwrap_or_default().to_string(),
+        ),
+    ]))
+}
+
+pub fn execute_set_fee_on_transfer(
+    deps: DepsMut,
+    info: MessageInfo,
+    contract: String,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    let addr = deps.api.addr_validate(&contract)?;
+    if enabled {
+        FEE_ON_TRANSFER.save(deps.storage, &addr, &true)?;
+    } else {
+        FEE_ON_TRANSFER.remove(deps.storage, &addr);
     }
 
     Ok(Response::new().add_attributes(vec![
-        attr("action", "instantiate"),
-        attr("default_timeout", msg.default_timeout.to_string()),
+        attr("action", "set_fee_on_transfer"),
+        attr("contract", addr),
+        attr("enabled", enabled.to_string()),
     ]))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(
+pub fn execute_claim_referral_fees(
     deps: DepsMut,
-    env: Env,
     info: MessageInfo,
-    msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    match msg {
-        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
-        ExecuteMsg::Transfer(msg) => {
-            let coin = match info.funds.len() {
-                0 => Err(PaymentError::NoFunds {}),
-                1 => {
-                    let coin = &info.funds[0];
-                    if coin.amount.is_zero() {
-                        Err(PaymentError::NoFunds {})
-                    } else {
-                        Ok(coin.clone())
-                    }
-                }
-                _ => Err(PaymentError::MultipleDenoms {}),
-            }?;
-            execute_transfer(deps, env, msg, Amount::Native(coin), info.sender)
+    let fees = referrer_fees(deps.storage, &info.sender)?;
+    if fees.iter().all(Amount::is_empty) {
+        return Err(ContractError::NoReferralFees {});
+    }
+
+    let mut res = Response::new()
+        .add_attribute("action", "claim_referral_fees")
+        .add_attribute("referrer", &info.sender);
+    for fee in fees {
+        REFERRER_FEES.remove(deps.storage, (&info.sender, &fee.denom()));
+        reduce_escrowed(deps.storage, &fee)?;
+        if !fee.is_empty() {
+            res = res
+                .add_attribute("fee", format!("{}{}", fee.amount(), fee.denom()))
+                .add_message(send_amount(fee, info.sender.to_string()));
         }
     }
+    Ok(res)
+}
+
+pub fn execute_set_token_metadata(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+    metadata: TokenMetadata,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    let denom = normalize_denom(deps.api, denom)?;
+    TOKEN_METADATA.save(deps.storage, &denom, &metadata)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_token_metadata"),
+        attr("denom", denom),
+        attr("symbol", metadata.symbol),
+    ]))
+}
+
+pub fn execute_remove_token_metadata(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    let denom = normalize_denom(deps.api, denom)?;
+    TOKEN_METADATA.remove(deps.storage, &denom);
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "remove_token_metadata"),
+        attr("denom", denom),
+    ]))
+}
+
+pub fn execute_sync_token_metadata(
+    deps: DepsMut,
+    contract: String,
+) -> Result<Response, ContractError> {
+    let addr = deps.api.addr_validate(&contract)?;
+    WHITE_LIST
+        .may_load(deps.storage, &addr)?
+        .ok_or(ContractError::NotOnAllowList)?;
+
+    let info: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(&addr, &Cw20QueryMsg::TokenInfo {})?;
+    // the marketing extension is optional, so a failing query just means no logo
+    let marketing: Option<MarketingInfoResponse> = deps
+        .querier
+        .query_wasm_smart(&addr, &Cw20QueryMsg::MarketingInfo {})
+        .ok();
+    let logo = marketing.and_then(|m| m.logo).map(|logo| match logo {
+        LogoInfo::Url(url) => url,
+        LogoInfo::Embedded => "embedded".to_string(),
+    });
+
+    let denom = format!("cw20:{}", addr);
+    let metadata = TokenMetadata {
+        symbol: info.symbol,
+        decimals: info.decimals,
+        logo,
+    };
+    TOKEN_METADATA.save(deps.storage, &denom, &metadata)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "sync_token_metadata"),
+        attr("denom", denom),
+        attr("symbol", metadata.symbol),
+    ]))
 }
 
 pub fn execute_receive(
@@ -79,84 +1066,590 @@ pub fn execute_receive(
     info: MessageInfo,
     wrapper: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
-    let msg: TransferMsg = from_binary(&wrapper.msg)?;
     let amount = Amount::Cw20(Cw20Coin {
         address: info.sender.to_string(),
         amount: wrapper.amount,
     });
+    if FEE_ON_TRANSFER.has(deps.storage, &info.sender) {
+        return Err(ContractError::FeeOnTransferToken {
+            contract: info.sender.into(),
+        });
+    }
     let api = deps.api;
-    execute_transfer(deps, env, msg, amount, api.addr_validate(&wrapper.sender)?)
+    // anything that is not one of the hooks is a plain transfer
+    if let Ok(hook) = from_binary::<ReceiveMsg>(&wrapper.msg) {
+        let sender = api.addr_validate(&wrapper.sender)?;
+        return match hook {
+            ReceiveMsg::ScheduleTransfer(msg) => {
+                execute_schedule_transfer(deps, env, msg, amount, sender)
+            }
+            ReceiveMsg::CreateStream(msg) => execute_create_stream(deps, env, msg, amount, sender),
+            ReceiveMsg::CreateConditional(msg) => {
+                execute_create_conditional(deps, env, msg, amount, sender)
+            }
+            ReceiveMsg::LockHtlc(msg) => execute_lock_htlc(deps, env, msg, amount, sender),
+            ReceiveMsg::Deposit { operator } => {
+                execute_deposit(deps, env, amount, sender, operator)
+            }
+        };
+    }
+    let msg: TransferMsg = from_binary(&wrapper.msg)?;
+    execute_transfer(
+        deps,
+        env,
+        msg,
+        amount,
+        api.addr_validate(&wrapper.sender)?,
+        None,
+    )
 }
+
 pub fn execute_transfer(
     deps: DepsMut,
     env: Env,
-    msg: TransferMsg,
+    mut msg: TransferMsg,
     amount: Amount,
     sender: Addr,
+    fee_coin: Option<Coin>,
 ) -> Result<Response, ContractError> {
     if amount.is_empty() {
         return Err(ContractError::NoFunds {});
     }
-    // ensure the requested channel is registered
-    if !CHANNEL_INFO.has(deps.storage, &msg.channel) {
-        return Err(ContractError::NoSuchChannel { id: msg.channel });
+    if active_pause(deps.storage, &env.block)?.is_some() {
+        return Err(ContractError::Paused {});
+    }
+    // a named route turns into the first forwarder as receiver and the forward memo chain
+    if let Some(name) = &msg.route {
+        if msg.memo.is_some() {
+            return Err(ContractError::MemoWithRoute {});
+        }
+        let route = ROUTES
+            .may_load(deps.storage, name)?
+            .ok_or_else(|| ContractError::NoSuchRoute { name: name.clone() })?;
+        if route.channel != msg.channel {
+            return Err(ContractError::RouteChannelMismatch {
+                name: name.clone(),
+                expected: route.channel,
+                channel: msg.channel,
+            });
+        }
+        let (receiver, memo) = route_memo(&route, &msg.remote_address)?;
+        msg.remote_address = receiver;
+        msg.memo = Some(memo);
+    }
+    if let Some(memo) = &msg.memo {
+        let max =
+            param_u64(deps.storage, PARAM_MAX_MEMO_LENGTH)?.unwrap_or(DEFAULT_MAX_MEMO_LENGTH);
+        if memo.len() as u64 > max {
+            return Err(ContractError::MemoTooLong {
+                length: memo.len() as u64,
+                max,
+            });
+        }
+    }
+    if let Some(requested) = &msg.denom {
+        let expected = resolve_denom(deps.storage, &msg.channel, requested)?;
+        if expected != amount.denom() {
+            return Err(ContractError::DenomMismatch {
+                sent: amount.denom(),
+                expected,
+            });
+        }
     }
 
-    // if cw20 token, ensure it is whitelisted
-    if let Amount::Cw20(coin) = &amount {
-        let addr = deps.api.addr_validate(&coin.address)?;
-        WHITE_LIST
-            .may_load(deps.storage, &addr)?
-            .ok_or(ContractError::NotOnAllowList)?;
-    };
+    let channel_cfg = CHANNEL_CONFIG
+        .may_load(deps.storage, &msg.channel)?
+        .unwrap_or_default();
+    let mut approval = None;
+    match channel_cfg.mode {
+        ChannelMode::Portal => {
+            // ensure the requested channel is registered
+            if !CHANNEL_INFO.has(deps.storage, &msg.channel) {
+                return Err(ContractError::NoSuchChannel { id: msg.channel });
+            }
 
-    // delta from user is in seconds
-    let timeout_delta = match msg.timeout {
-        Some(t) => t,
-        None => CONFIG.load(deps.storage)?.default_timeout,
-    };
-    // timeout is in nanoseconds
-    let timeout = env.block.time.plus_seconds(timeout_delta);
+            // if cw20 token, ensure it is whitelisted or approved by the verifier
+            if let Amount::Cw20(coin) = &amount {
+                let addr = deps.api.addr_validate(&coin.address)?;
+                approval = check_cw20(deps.as_ref(), env.block.time, &addr)?.map(|a| (addr, a));
+            };
+        }
+        ChannelMode::NativeTransfer => {
+            if let Amount::Cw20(_) = &amount {
+                return Err(ContractError::OnlyNativeOnChannel { id: msg.channel });
+            }
+            // the transfer module message has no memo field
+            if msg.memo.is_some() {
+                return Err(ContractError::MemoNotSupported { id: msg.channel });
+            }
+        }
+    }
+    if let Amount::Native(coin) = &amount {
+        if !native_denom_allowed(deps.storage, &coin.denom)? {
+            return Err(ContractError::NativeNotAllowed {
+                denom: coin.denom.clone(),
+            });
+        }
+    }
+    // the channel's own allowlist narrows things down further
+    if channel_cfg.token_allowlist
+        && !CHANNEL_ALLOWLIST.has(deps.storage, (&msg.channel, &amount.denom()))
+    {
+        return Err(ContractError::NotAllowedOnChannel {
+            denom: amount.denom(),
+            channel: msg.channel,
+        });
+    }
+
+    let referrer = msg
+        .referrer
+        .as_deref()
+        .map(|r| deps.api.addr_validate(r))
+        .transpose()?;
+    // the sender would get part of its own fee back
+    if referrer.as_ref() == Some(&sender) {
+        return Err(ContractError::SelfReferral {});
+    }
+
+    let fee = protocol_fee(
+        deps.as_ref(),
+        &env,
+        amount,
+        &sender,
+        msg.fee_in_fee_denom,
+        fee_coin,
+    )?;
+    let amount = fee.net;
+    let timeout = transfer_timeout(deps.as_ref(), &env, &msg)?;
 
     // build ics20 packet
-    let packet = Ics20Packet::new(
-        amount.amount(),
-        amount.denom(),
-        sender.as_ref(),
-        &msg.remote_address,
-    );
-    packet.validate()?;
+    let portal_packet = match channel_cfg.mode {
+        ChannelMode::Portal => {
+            let packet = Ics20Packet::new(
+                amount.amount(),
+                amount.denom(),
+                sender.as_ref(),
+                &msg.remote_address,
+            )
+            .with_memo(msg.memo.clone());
+            packet.validate()?;
+            let data = to_binary(&packet)?;
+            if let Some(max) = param_u64(deps.storage, PARAM_MAX_PACKET_SIZE)? {
+                let size = data.len() as u64;
+                if size > max {
+                    return Err(ContractError::PacketTooLarge { size, max });
+                }
+            }
+            Some((packet, data))
+        }
+        ChannelMode::NativeTransfer => None,
+    };
+
+    // Nothing below may reject the transfer. The cranks keep what a failed transfer wrote,
+    // so every check has to come before the first write.
+    if let Some((token, approval)) = &approval {
+        VERIFIER_APPROVALS.save(deps.storage, token, approval)?;
+    }
+    let fee_tier = fee.tier.map(|idx| idx.to_string());
+    let mut res = Response::new().add_messages(fee.messages);
+    if let Some(fee) = fee.fee {
+        accrue_fee(deps.storage, &fee)?;
+        res = res.add_attributes(vec![
+            attr("fee_amount", fee.amount().to_string()),
+            attr("fee_denom", fee.denom()),
+        ]);
+        if let Some(referrer) = &referrer {
+            let share = credit_referrer(deps.storage, referrer, &fee)?;
+            if !share.is_zero() {
+                res = res.add_attributes(vec![
+                    attr("referrer", referrer),
+                    attr("referrer_fee", share.to_string()),
+                ]);
+            }
+        }
+    }
+    if let Some(tier) = &fee_tier {
+        res = res.add_attribute("fee_tier", tier);
+    }
+
+    let (packet, data) = match portal_packet {
+        Some(portal_packet) => portal_packet,
+        None => return execute_native_transfer(deps, env, msg, amount, sender, timeout, res),
+    };
 
     // Update the balance now (optimistically) like ibctransfer modules.
     // In on_packet_failure (ack with error message or a timeout), we reduce the balance appropriately.
     // This means the channel works fine if success acks are not relayed.
     increase_channel_balance(deps.storage, &msg.channel, &amount.denom(), amount.amount())?;
+    if let Amount::Cw20(coin) = &amount {
+        let token = Addr::unchecked(&coin.address);
+        record_token_use(deps.storage, &token, coin.amount, env.block.time)?;
+    }
+
+    update_metrics(deps.storage, |m| m.packets_sent += 1)?;
+    let transfer_id =
+        save_transfer_record(deps.storage, &msg.channel, &packet, &data, env.block.height)?;
+
+    // same keys as the transfer module. The sequence is only assigned when the chain sends the
+    // packet, it is in the send_packet event
+    let mut event = Event::new(EVENT_TRANSFER)
+        .add_attribute("sender", &packet.sender)
+        .add_attribute("receiver", &packet.receiver)
+        .add_attribute("denom", &packet.denom)
+        .add_attribute("amount", packet.amount.to_string())
+        .add_attribute("memo", packet.memo.clone().unwrap_or_default())
+        .add_attribute("packet_data", String::from_utf8_lossy(data.as_slice()))
+        .add_attribute("packet_src_channel", &msg.channel)
+        .add_attribute("packet_timeout_timestamp", timeout.nanos().to_string());
+    if let Some(tier) = fee_tier {
+        event = event.add_attribute("fee_tier", tier);
+    }
 
     // prepare ibc message
     let msg = IbcMsg::SendPacket {
         channel_id: msg.channel,
-        data: to_binary(&packet)?,
+        data,
         timeout: timeout.into(),
     };
 
     // send response
-    Ok(Response::new().add_message(msg).add_attributes(vec![
+    Ok(res.add_message(msg).add_event(event).add_attributes(vec![
         attr("action", "transfer"),
+        attr("transfer_id", transfer_id.to_string()),
         attr("sender", &packet.sender),
         attr("receiver", &packet.receiver),
         attr("denom", &packet.denom),
-        attr("amount", &packet.amount.to_string()),
+        attr("amount", packet.amount.to_string()),
     ]))
 }
 
+// Hands native coins to the chain's transfer module, which escrows them. The module is the
+// only one to see the ack or timeout and refunds a failed transfer to us as its ICS-20 sender,
+// so the transfer is recorded and held in ESCROWED until the owner resolves it.
+fn execute_native_transfer(
+    deps: DepsMut,
+    env: Env,
+    msg: TransferMsg,
+    amount: Amount,
+    sender: Addr,
+    timeout: Timestamp,
+    res: Response,
+) -> Result<Response, ContractError> {
+    let coin = match amount {
+        Amount::Native(coin) => coin,
+        Amount::Cw20(_) => return Err(ContractError::OnlyNativeOnChannel { id: msg.channel }),
+    };
+    update_metrics(deps.storage, |m| m.packets_sent += 1)?;
+
+    let id = NATIVE_TRANSFER_COUNT
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        + 1;
+    NATIVE_TRANSFER_COUNT.save(deps.storage, &id)?;
+    let transfer = NativeTransfer {
+        id,
+        channel: msg.channel.clone(),
+        sender,
+        receiver: msg.remote_address.clone(),
+        amount: coin.clone(),
+        sequence: None,
+        height: env.block.height,
+    };
+    NATIVE_TRANSFERS.save(deps.storage, id.into(), &transfer)?;
+    increase_escrowed(deps.storage, &Amount::Native(coin.clone()))?;
+    let mut awaiting = NATIVE_AWAITING_SEQUENCE
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    awaiting.push(id);
+    NATIVE_AWAITING_SEQUENCE.save(deps.storage, &awaiting)?;
+
+    // the transfer module emits its own ibc_transfer event with the packet data
+    let res = res.add_attributes(vec![
+        attr("action", "transfer"),
+        attr("mode", "native_transfer"),
+        attr("native_transfer_id", id.to_string()),
+        attr("sender", transfer.sender),
+        attr("receiver", &msg.remote_address),
+        attr("denom", &coin.denom),
+        attr("amount", coin.amount.to_string()),
+    ]);
+    let msg = IbcMsg::Transfer {
+        channel_id: msg.channel,
+        to_address: msg.remote_address,
+        amount: coin,
+        timeout: timeout.into(),
+    };
+    Ok(res.add_submessage(SubMsg::reply_on_success(msg, NATIVE_TRANSFER_ID)))
+}
+
+/// Notes the sequence the transfer module gave the oldest native transfer still waiting for one.
+/// A chain that doesn't report it leaves the sequence unset rather than failing the transfer
+pub(crate) fn record_native_sequence(
+    storage: &mut dyn Storage,
+    events: &[Event],
+) -> Result<Response, ContractError> {
+    let mut awaiting = NATIVE_AWAITING_SEQUENCE
+        .may_load(storage)?
+        .unwrap_or_default();
+    if awaiting.is_empty() {
+        return Ok(Response::new());
+    }
+    let id = awaiting.remove(0);
+    if awaiting.is_empty() {
+        NATIVE_AWAITING_SEQUENCE.remove(storage);
+    } else {
+        NATIVE_AWAITING_SEQUENCE.save(storage, &awaiting)?;
+    }
+    let sequence = events
+        .iter()
+        .filter(|e| e.ty == "send_packet")
+        .flat_map(|e| e.attributes.iter())
+        .find(|a| a.key == "packet_sequence")
+        .and_then(|a| a.value.parse::<u64>().ok());
+    let mut res = Response::new().add_attribute("native_transfer_id", id.to_string());
+    if let Some(sequence) = sequence {
+        NATIVE_TRANSFERS.update(storage, id.into(), |transfer| -> StdResult<_> {
+            let mut transfer = transfer.ok_or_else(|| StdError::not_found("NativeTransfer"))?;
+            transfer.sequence = Some(sequence);
+            Ok(transfer)
+        })?;
+        res = res.add_attribute("packet_sequence", sequence.to_string());
+    }
+    Ok(res)
+}
+
+pub fn execute_resolve_native_transfer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    resolution: NativeTransferResolution,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), &info.sender)?;
+    let transfer = NATIVE_TRANSFERS
+        .may_load(deps.storage, id.into())?
+        .ok_or(ContractError::NoSuchNativeTransfer { id })?;
+    NATIVE_TRANSFERS.remove(deps.storage, id.into());
+    let amount = Amount::Native(transfer.amount);
+    reduce_escrowed(deps.storage, &amount)?;
+
+    let res = Response::new().add_attributes(vec![
+        attr("action", "resolve_native_transfer"),
+        attr("native_transfer_id", id.to_string()),
+    ]);
+    let res = match resolution {
+        NativeTransferResolution::Delivered {} => res.add_attribute("resolution", "delivered"),
+        // treasury transfers were sent by the contract, their refund stays where it is
+        NativeTransferResolution::Refunded {} if transfer.sender == env.contract.address => {
+            res.add_attribute("resolution", "refunded")
+        }
+        NativeTransferResolution::Refunded {} => res
+            .add_attribute("resolution", "refunded")
+            .add_attribute("recipient", &transfer.sender)
+            .add_message(send_amount(amount, transfer.sender.into())),
+    };
+    Ok(res)
+}
+
+fn transfer_timeout(deps: Deps, env: &Env, msg: &TransferMsg) -> Result<Timestamp, ContractError> {
+    // delta from user is in seconds
+    let requested = checked_timeout(deps.storage, msg.timeout, msg.timeout_duration.as_deref())?;
+    let timeout_delta = match requested {
+        Some(timeout) => timeout,
+        // the default may predate a lower max_timeout
+        None => CONFIG
+            .load(deps.storage)?
+            .default_timeout
+            .min(max_timeout(deps.storage)?),
+    };
+    // timeout is in nanoseconds
+    Ok(env.block.time.plus_seconds(timeout_delta))
+}
+
+/// The requested packet timeout in seconds, given either in seconds or as a duration and at most
+/// max_timeout. None leaves it to default_timeout
+pub(crate) fn checked_timeout(
+    storage: &dyn Storage,
+    timeout: Option<u64>,
+    duration: Option<&str>,
+) -> Result<Option<u64>, ContractError> {
+    let requested = match (timeout, duration) {
+        (Some(_), Some(_)) => return Err(ContractError::ConflictingTimeouts {}),
+        (Some(t), None) => t,
+        (None, Some(duration)) => parse_duration(duration)?,
+        (None, None) => return Ok(None),
+    };
+    let max = max_timeout(storage)?;
+    if requested > max {
+        return Err(ContractError::TimeoutTooLong {
+            timeout: requested,
+            max,
+        });
+    }
+    Ok(Some(requested))
+}
+
+fn max_timeout(storage: &dyn Storage) -> StdResult<u64> {
+    Ok(param_u64(storage, PARAM_MAX_TIMEOUT)?.unwrap_or(DEFAULT_MAX_TIMEOUT))
+}
+
+/// Parses durations like "90s", "15m", "2h" or "1h30m" into seconds. Units are s, m, h and d,
+/// so milliseconds ("ms") and bare numbers are rejected
+pub fn parse_duration(duration: &str) -> Result<u64, ContractError> {
+    let invalid = || ContractError::InvalidDuration {
+        duration: duration.to_string(),
+    };
+    let mut total: u64 = 0;
+    let mut number: Option<u64> = None;
+    for c in duration.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            let n = number
+                .unwrap_or_default()
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(digit as u64))
+                .ok_or_else(invalid)?;
+            number = Some(n);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let n = number.take().ok_or_else(invalid)?;
+        total = n
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+    }
+    if number.is_some() || total == 0 {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Port {} => to_binary(&query_port(deps)?),
         QueryMsg::ListChannels {} => to_binary(&query_list(deps)?),
         QueryMsg::Channel { id } => to_binary(&query_channel(deps, id)?),
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
-        QueryMsg::Whitelisted { contract } => to_binary(&query_whitelisted(deps, contract)?),
+        QueryMsg::ConfigHistory { start_after, limit } => {
+            to_binary(&query_config_history(deps, start_after, limit)?)
+        }
+        QueryMsg::Whitelisted { contract } => to_binary(&query_whitelisted(deps, &env, contract)?),
+        QueryMsg::WhitelistVerifier {} => to_binary(&WhitelistVerifierResponse {
+            verifier: WHITELIST_VERIFIER.may_load(deps.storage)?,
+        }),
+        QueryMsg::WhitelistedBatch { contracts } => {
+            to_binary(&query_whitelisted_batch(deps, &env, contracts)?)
+        }
+        QueryMsg::ChannelMode { id } => to_binary(&query_channel_mode(deps, id)?),
+        QueryMsg::AckFormat { channel } => to_binary(&query_ack_format(deps, channel)?),
+        QueryMsg::VerifyRemoteAddress { channel, address } => {
+            to_binary(&query_verify_remote_address(deps, channel, address)?)
+        }
+        QueryMsg::Params { start_after, limit } => {
+            to_binary(&query_params(deps, start_after, limit)?)
+        }
+        QueryMsg::Pause {} => to_binary(&query_pause(deps, env)?),
+        QueryMsg::DeadLetters { start_after, limit } => {
+            to_binary(&query_dead_letters(deps, start_after, limit)?)
+        }
+        QueryMsg::ResolveDenom { channel, alias } => {
+            to_binary(&query_resolve_denom(deps, channel, alias)?)
+        }
+        QueryMsg::DenomAliases {
+            channel,
+            start_after,
+            limit,
+        } => to_binary(&query_denom_aliases(deps, channel, start_after, limit)?),
+        QueryMsg::FeeConfig {} => to_binary(&FeeConfigResponse {
+            fee_config: FEE_CONFIG.may_load(deps.storage)?,
+        }),
+        QueryMsg::FeeTiers { denom } => {
+            let denom = normalize_denom(deps.api, denom)?;
+            to_binary(&FeeTiersResponse {
+                tiers: FEE_TIERS
+                    .may_load(deps.storage, &denom)?
+                    .unwrap_or_default(),
+                denom,
+            })
+        }
+        QueryMsg::ReferralFees { referrer } => {
+            let referrer = deps.api.addr_validate(&referrer)?;
+            to_binary(&ReferralFeesResponse {
+                fees: referrer_fees(deps.storage, &referrer)?,
+                referrer: referrer.into(),
+            })
+        }
+        QueryMsg::TreasuryLog { start_after, limit } => {
+            to_binary(&query_treasury_log(deps, start_after, limit)?)
+        }
+        QueryMsg::CollectedFees {} => to_binary(&CollectedFeesResponse {
+            fees: collected_fees(deps.storage)?,
+        }),
+        QueryMsg::ChannelAllowlist {
+            channel,
+            start_after,
+            limit,
+        } => to_binary(&query_channel_allowlist(deps, channel, start_after, limit)?),
+        QueryMsg::NativeAllowlist { start_after, limit } => {
+            to_binary(&query_native_allowlist(deps, start_after, limit)?)
+        }
+        QueryMsg::Schedule { id } => to_binary(&query_schedule(deps, id)?),
+        QueryMsg::SchedulesBySender {
+            sender,
+            start_after,
+            limit,
+        } => to_binary(&query_schedules_by_sender(
+            deps,
+            sender,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::Stream { id } => to_binary(&query_stream(deps, id)?),
+        QueryMsg::Conditional { id } => to_binary(&query_conditional(deps, id)?),
+        QueryMsg::Deposit { id } => to_binary(&query_deposit(deps, id)?),
+        QueryMsg::DepositsBySender {
+            sender,
+            start_after,
+            limit,
+        } => to_binary(&query_deposits_by_sender(deps, sender, start_after, limit)?),
+        QueryMsg::Oracles { start_after, limit } => {
+            to_binary(&query_oracles(deps, start_after, limit)?)
+        }
+        QueryMsg::Route { name } => to_binary(&ROUTES.load(deps.storage, &name)?),
+        QueryMsg::Routes { start_after, limit } => {
+            to_binary(&query_routes(deps, start_after, limit)?)
+        }
+        QueryMsg::TransfersByReceiver {
+            receiver,
+            start_after,
+            limit,
+        } => to_binary(&query_transfers_by_receiver(
+            deps,
+            receiver,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::Metrics {} => to_binary(&query_metrics(deps)?),
+        QueryMsg::Htlc { id } => to_binary(&query_htlc(deps, id)?),
+        QueryMsg::HtlcsByHash {
+            hash,
+            start_after,
+            limit,
+        } => to_binary(&query_htlcs_by_hash(deps, hash, start_after, limit)?),
+        QueryMsg::TokenMetadata { denom } => to_binary(&query_token_metadata(deps, denom)?),
+        QueryMsg::ListTokenMetadata { start_after, limit } => {
+            to_binary(&query_list_token_metadata(deps, start_after, limit)?)
+        }
+        QueryMsg::NativeTransfers { start_after, limit } => {
+            to_binary(&query_native_transfers(deps, start_after, limit)?)
+        }
     }
 }
 
@@ -202,22 +1695,395 @@ pub fn query_channel(deps: Deps, id: String) -> StdResult<ChannelResponse> {
     })
 }
 
+fn query_transfers_by_receiver(
+    deps: Deps,
+    receiver: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<TransfersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+    let transfers: StdResult<Vec<_>> = TRANSFERS_BY_RECEIVER
+        .prefix(&receiver)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|k| {
+            let id: [u8; 8] = k.as_slice().try_into().unwrap();
+            TRANSFERS.load(deps.storage, u64::from_be_bytes(id).into())
+        })
+        .collect();
+    Ok(TransfersResponse {
+        transfers: transfers?,
+    })
+}
+
+fn query_metrics(deps: Deps) -> StdResult<MetricsResponse> {
+    let metrics = METRICS.may_load(deps.storage)?.unwrap_or_default();
+    let fees_collected: StdResult<Vec<_>> = LIFETIME_FEES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|r| {
+            let (denom, amount) = r?;
+            Ok(Amount::from_parts(String::from_utf8(denom)?, amount))
+        })
+        .collect();
+    Ok(MetricsResponse {
+        packets_sent: metrics.packets_sent,
+        packets_received: metrics.packets_received,
+        packets_acked: metrics.packets_acked,
+        packets_timed_out: metrics.packets_timed_out,
+        packets_refunded: metrics.packets_refunded,
+        fees_collected: fees_collected?,
+        active_channels: metrics.channels_opened,
+        instantiate_height: metrics.instantiate_height,
+    })
+}
+
+fn query_config_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ConfigHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+    let versions: StdResult<Vec<_>> = CONFIG_HISTORY
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|r| r.map(|(_, version)| version))
+        .collect();
+    Ok(ConfigHistoryResponse {
+        versions: versions?,
+    })
+}
+
+fn query_treasury_log(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<TreasuryLogResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+    let entries: StdResult<Vec<_>> = TREASURY_LOG
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|r| r.map(|(_, entry)| entry))
+        .collect();
+    Ok(TreasuryLogResponse { entries: entries? })
+}
+
 fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let cfg = CONFIG.load(deps.storage)?;
     let res = ConfigResponse {
         default_timeout: cfg.default_timeout,
+        owner: cfg.owner.into(),
     };
     Ok(res)
 }
 
-fn query_whitelisted(deps: Deps, contract: String) -> StdResult<WhitelistResponse> {
+fn query_whitelisted(deps: Deps, env: &Env, contract: String) -> StdResult<WhitelistResponse> {
     let addr = deps.api.addr_validate(&contract)?;
-    let info = WHITE_LIST.may_load(deps.storage, &addr)?;
-    let res = match info {
-        None => WhitelistResponse {
-            is_whitelist: false,
+    // a token the verifier approves can be sent like a whitelisted one
+    let is_whitelist = match check_cw20(deps, env.block.time, &addr) {
+        Ok(_) => true,
+        Err(ContractError::Std(err)) => return Err(err),
+        Err(_) => false,
+    };
+    Ok(WhitelistResponse {
+        is_whitelist,
+        fee_on_transfer: FEE_ON_TRANSFER.has(deps.storage, &addr),
+        stats: TOKEN_STATS.may_load(deps.storage, &addr)?,
+    })
+}
+
+fn query_whitelisted_batch(
+    deps: Deps,
+    env: &Env,
+    contracts: Vec<String>,
+) -> StdResult<WhitelistedBatchResponse> {
+    // every contract may cost a verifier query
+    if contracts.len() > MAX_LIMIT as usize {
+        return Err(StdError::generic_err(format!(
+            "At most {} contracts can be queried at once",
+            MAX_LIMIT
+        )));
+    }
+    let results = contracts
+        .into_iter()
+        .map(
+            |contract| match query_whitelisted(deps, env, contract.clone()) {
+                Ok(res) => WhitelistedBatchItem {
+                    contract,
+                    is_whitelist: res.is_whitelist,
+                    fee_on_transfer: res.fee_on_transfer,
+                    stats: res.stats,
+                    error: None,
+                },
+                // one bad address should not hide the answers for the others
+                Err(err) => WhitelistedBatchItem {
+                    contract,
+                    is_whitelist: false,
+                    fee_on_transfer: false,
+                    stats: None,
+                    error: Some(err.to_string()),
+                },
+            },
+        )
+        .collect();
+    Ok(WhitelistedBatchResponse { results })
+}
+
+fn query_channel_mode(deps: Deps, id: String) -> StdResult<ChannelModeResponse> {
+    let cfg = CHANNEL_CONFIG
+        .may_load(deps.storage, &id)?
+        .unwrap_or_default();
+    Ok(ChannelModeResponse { mode: cfg.mode })
+}
+
+fn query_ack_format(deps: Deps, channel: String) -> StdResult<AckFormatResponse> {
+    let cfg = CHANNEL_CONFIG
+        .may_load(deps.storage, &channel)?
+        .unwrap_or_default();
+    Ok(AckFormatResponse {
+        format: cfg.ack_format,
+    })
+}
+
+fn query_verify_remote_address(
+    deps: Deps,
+    channel: String,
+    address: String,
+) -> StdResult<VerifyRemoteAddressResponse> {
+    let cfg = CHANNEL_CONFIG
+        .may_load(deps.storage, &channel)?
+        .unwrap_or_default();
+    let mut issues = vec![];
+    if !is_known_channel(deps.storage, &channel)? {
+        issues.push(AddressIssue::UnknownChannel {});
+    }
+    issues.extend(verify_remote_address(
+        &address,
+        cfg.remote_prefix.as_deref(),
+    ));
+    Ok(VerifyRemoteAddressResponse {
+        valid: issues.is_empty(),
+        expected_prefix: cfg.remote_prefix,
+        issues,
+    })
+}
+
+fn query_params(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ParamsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let params: StdResult<Vec<_>> = PARAMS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|r| {
+            let (k, value) = r?;
+            let key = String::from_utf8(k)?;
+            Ok(ParamInfo { key, value })
+        })
+        .collect();
+    Ok(ParamsResponse { params: params? })
+}
+
+fn query_oracles(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<OraclesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let oracles: StdResult<Vec<_>> = ORACLES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|r| {
+            let (k, address) = r?;
+            let name = String::from_utf8(k)?;
+            Ok(OracleInfo {
+                name,
+                address: address.into(),
+            })
+        })
+        .collect();
+    Ok(OraclesResponse { oracles: oracles? })
+}
+
+fn query_routes(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<RoutesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let routes: StdResult<Vec<_>> = ROUTES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|r| {
+            let (k, route) = r?;
+            let name = String::from_utf8(k)?;
+            Ok(RouteInfo { name, route })
+        })
+        .collect();
+    Ok(RoutesResponse { routes: routes? })
+}
+
+fn query_pause(deps: Deps, env: Env) -> StdResult<PauseResponse> {
+    let res = match active_pause(deps.storage, &env.block)? {
+        Some(pause) => PauseResponse {
+            paused: true,
+            source: Some(pause.source),
+            expires: Some(pause.expires),
+        },
+        None => PauseResponse {
+            paused: false,
+            source: None,
+            expires: None,
         },
-        Some(_) => WhitelistResponse { is_whitelist: true },
     };
     Ok(res)
 }
+
+fn query_native_transfers(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<NativeTransfersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+    let transfers: StdResult<Vec<_>> = NATIVE_TRANSFERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|r| r.map(|(_, v)| v))
+        .collect();
+    Ok(NativeTransfersResponse {
+        transfers: transfers?,
+    })
+}
+
+fn query_dead_letters(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<DeadLettersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+    let dead_letters: StdResult<Vec<_>> = DEAD_LETTERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|r| r.map(|(_, v)| v))
+        .collect();
+    Ok(DeadLettersResponse {
+        dead_letters: dead_letters?,
+    })
+}
+
+fn query_resolve_denom(
+    deps: Deps,
+    channel: String,
+    alias: String,
+) -> StdResult<ResolveDenomResponse> {
+    let denom = resolve_denom(deps.storage, &channel, &alias)?;
+    Ok(ResolveDenomResponse {
+        is_alias: denom != alias,
+        denom,
+    })
+}
+
+fn query_denom_aliases(
+    deps: Deps,
+    channel: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<DenomAliasesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let aliases: StdResult<Vec<_>> = DENOM_ALIASES
+        .prefix(&channel)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|r| {
+            let (k, denom) = r?;
+            let alias = String::from_utf8(k)?;
+            Ok(DenomAlias { alias, denom })
+        })
+        .collect();
+    Ok(DenomAliasesResponse { aliases: aliases? })
+}
+
+fn query_token_metadata(deps: Deps, denom: String) -> StdResult<TokenMetadataResponse> {
+    let denom = normalize_denom(deps.api, denom)?;
+    let metadata = TOKEN_METADATA.may_load(deps.storage, &denom)?;
+    Ok(TokenMetadataResponse { denom, metadata })
+}
+
+fn query_list_token_metadata(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListTokenMetadataResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let tokens: StdResult<Vec<_>> = TOKEN_METADATA
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|r| {
+            let (k, metadata) = r?;
+            let denom = String::from_utf8(k)?;
+            Ok(TokenMetadataResponse {
+                denom,
+                metadata: Some(metadata),
+            })
+        })
+        .collect();
+    Ok(ListTokenMetadataResponse { tokens: tokens? })
+}
+
+fn query_native_allowlist(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<NativeAllowlistResponse> {
+    let enabled = NATIVE_ALLOWLIST_ENABLED
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let denoms: StdResult<Vec<_>> = NATIVE_ALLOWLIST
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(String::from_utf8)
+        .map(|r| r.map_err(StdError::from))
+        .collect();
+    Ok(NativeAllowlistResponse {
+        enabled,
+        denoms: denoms?,
+    })
+}
+
+fn query_channel_allowlist(
+    deps: Deps,
+    channel: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ChannelAllowlistResponse> {
+    let enabled = CHANNEL_CONFIG
+        .may_load(deps.storage, &channel)?
+        .unwrap_or_default()
+        .token_allowlist;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let tokens: StdResult<Vec<_>> = CHANNEL_ALLOWLIST
+        .prefix(&channel)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(String::from_utf8)
+        .map(|r| r.map_err(StdError::from))
+        .collect();
+    Ok(ChannelAllowlistResponse {
+        enabled,
+        tokens: tokens?,
+    })
+}
@@ -0,0 +1,155 @@
+use crate::amount::Amount;
+use crate::contract::{execute_transfer, DEFAULT_LIMIT, MAX_LIMIT};
+use crate::error::ContractError;
+use crate::ibc::send_amount;
+use crate::msg::{DepositsResponse, TransferMsg};
+use crate::state::{
+    increase_escrowed, reduce_escrowed, Deposit, DEPOSITS, DEPOSITS_BY_SENDER, DEPOSIT_COUNT,
+};
+use cosmwasm_std::{
+    attr, Addr, Coin, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult, Storage,
+};
+use cw_storage_plus::{Bound, U64Key};
+use std::convert::TryInto;
+
+/// Holds the funds until the depositor or its operator releases them. Nothing is checked against
+/// a channel yet, release goes through the same checks as a direct transfer.
+pub fn execute_deposit(
+    deps: DepsMut,
+    env: Env,
+    amount: Amount,
+    sender: Addr,
+    operator: Option<String>,
+) -> Result<Response, ContractError> {
+    if amount.is_empty() {
+        return Err(ContractError::NoFunds {});
+    }
+    let operator = operator.map(|op| deps.api.addr_validate(&op)).transpose()?;
+    let id = DEPOSIT_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+    DEPOSIT_COUNT.save(deps.storage, &id)?;
+    let deposit = Deposit {
+        id,
+        sender,
+        operator,
+        amount,
+        created: env.block.time,
+    };
+    DEPOSITS.save(deps.storage, id.into(), &deposit)?;
+    DEPOSITS_BY_SENDER.save(deps.storage, (&deposit.sender, id.into()), &true)?;
+    increase_escrowed(deps.storage, &deposit.amount)?;
+
+    let mut attrs = vec![
+        attr("action", "deposit"),
+        attr("deposit_id", id.to_string()),
+        attr("sender", &deposit.sender),
+        attr("denom", deposit.amount.denom()),
+        attr("amount", deposit.amount.amount().to_string()),
+    ];
+    if let Some(operator) = &deposit.operator {
+        attrs.push(attr("operator", operator));
+    }
+    Ok(Response::new().add_attributes(attrs))
+}
+
+/// Replaces or clears who besides the depositor may release. Only callable by the depositor
+pub fn execute_set_deposit_operator(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+    operator: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut deposit = load_deposit(deps.storage, id)?;
+    if deposit.sender != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    deposit.operator = operator.map(|op| deps.api.addr_validate(&op)).transpose()?;
+    DEPOSITS.save(deps.storage, id.into(), &deposit)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_deposit_operator"),
+        attr("deposit_id", id.to_string()),
+        attr(
+            "operator",
+            deposit.operator.map(String::from).unwrap_or_default(),
+        ),
+    ]))
+}
+
+/// Sends the deposit as a regular transfer from the depositor. Callable by the depositor or
+/// its operator. A rejected transfer leaves the deposit in place.
+pub fn execute_release(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    msg: TransferMsg,
+    fee_coin: Option<Coin>,
+) -> Result<Response, ContractError> {
+    let deposit = load_deposit(deps.storage, id)?;
+    if deposit.sender != info.sender && deposit.operator.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    remove_deposit(deps.storage, &deposit)?;
+    let res = execute_transfer(deps, env, msg, deposit.amount, deposit.sender, fee_coin)?;
+    Ok(res
+        .add_attribute("deposit_id", id.to_string())
+        .add_attribute("released_by", info.sender))
+}
+
+/// Refunds the deposit. Only callable by the depositor
+pub fn execute_cancel_deposit(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let deposit = load_deposit(deps.storage, id)?;
+    if deposit.sender != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    remove_deposit(deps.storage, &deposit)?;
+
+    Ok(Response::new()
+        .add_message(send_amount(deposit.amount, deposit.sender.to_string()))
+        .add_attributes(vec![
+            attr("action", "cancel_deposit"),
+            attr("deposit_id", id.to_string()),
+            attr("sender", deposit.sender),
+        ]))
+}
+
+fn load_deposit(storage: &dyn Storage, id: u64) -> Result<Deposit, ContractError> {
+    DEPOSITS
+        .may_load(storage, id.into())?
+        .ok_or(ContractError::NoSuchDeposit { id })
+}
+
+fn remove_deposit(storage: &mut dyn Storage, deposit: &Deposit) -> StdResult<()> {
+    DEPOSITS_BY_SENDER.remove(storage, (&deposit.sender, deposit.id.into()));
+    DEPOSITS.remove(storage, deposit.id.into());
+    reduce_escrowed(storage, &deposit.amount)
+}
+
+pub fn query_deposit(deps: Deps, id: u64) -> StdResult<Deposit> {
+    DEPOSITS.load(deps.storage, id.into())
+}
+
+pub fn query_deposits_by_sender(
+    deps: Deps,
+    sender: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<DepositsResponse> {
+    let sender = deps.api.addr_validate(&sender)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| Bound::exclusive(U64Key::new(id)));
+    let deposits = DEPOSITS_BY_SENDER
+        .prefix(&sender)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|k| {
+            let id: [u8; 8] = k.as_slice().try_into().unwrap();
+            DEPOSITS.load(deps.storage, u64::from_be_bytes(id).into())
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(DepositsResponse { deposits })
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use cw0::PaymentError;
 use thiserror::Error;
 
@@ -31,6 +31,121 @@ pub enum ContractError {
     FromOtherPort { port: String },
     #[error("Parsed channel from denom ({channel}) doesn't match packet")]
     FromOtherChannel { channel: String },
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+    #[error("Transfers are paused")]
+    Paused {},
+    #[error("Only {available} of the contract's balance is free to spend")]
+    InsufficientTreasury { available: Uint128 },
+    #[error("{contract} taxes transfers, send it with TransferCw20")]
+    FeeOnTransferToken { contract: String },
+    #[error("Native denom {denom} is not on the allowlist")]
+    NativeNotAllowed { denom: String },
+    #[error("{denom} is not allowed on channel {channel}")]
+    NotAllowedOnChannel { denom: String, channel: String },
+    #[error("Scheduled transfer doesn't exist: {id}")]
+    NoSuchSchedule { id: u64 },
+    #[error("Schedule needs a non-zero amount and interval, and funds for at least one run")]
+    InvalidSchedule {},
+    #[error("Stream doesn't exist: {id}")]
+    NoSuchStream { id: u64 },
+    #[error("Stream needs funds and a non-zero duration")]
+    InvalidStream {},
+    #[error("Stream {id} is paused")]
+    StreamPaused { id: u64 },
+    #[error("Stream {id} is not paused")]
+    StreamNotPaused { id: u64 },
+    #[error("Oracle isn't registered: {name}")]
+    NoSuchOracle { name: String },
+    #[error("Route doesn't exist: {name}")]
+    NoSuchRoute { name: String },
+    #[error("A route needs a known channel and at least one hop")]
+    InvalidRoute {},
+    #[error("Route {name} starts on {expected}, not {channel}")]
+    RouteChannelMismatch {
+        name: String,
+        expected: String,
+        channel: String,
+    },
+    #[error("A routed transfer builds its own memo")]
+    MemoWithRoute {},
+    #[error("Conditional transfer doesn't exist: {id}")]
+    NoSuchConditional { id: u64 },
+    #[error("Conditional transfer needs funds and an expiration in the future")]
+    InvalidConditional {},
+    #[error("Oracle reports the condition is not met")]
+    ConditionNotMet {},
+    #[error("Conditional transfer has expired")]
+    ConditionalExpired {},
+    #[error("Conditional transfer has not expired yet")]
+    ConditionalNotExpired {},
+    #[error("Deposit doesn't exist: {id}")]
+    NoSuchDeposit { id: u64 },
+    #[error("Htlc needs a hex encoded sha256 hash and an expiration in the future")]
+    InvalidHtlc {},
+    #[error("Htlc doesn't exist: {id}")]
+    NoSuchHtlc { id: u64 },
+    #[error("Htlc has expired")]
+    HtlcExpired {},
+    #[error("Htlc has not expired yet")]
+    HtlcNotExpired {},
+    #[error("Channel {id} uses the native transfer module and cannot carry a memo")]
+    MemoNotSupported { id: String },
+    #[error("Channel {id} uses the native transfer module and only accepts native coins")]
+    OnlyNativeOnChannel { id: String },
+    #[error("Parameter key must not be empty")]
+    EmptyParamKey {},
+    #[error("Dead letter doesn't exist: {id}")]
+    NoSuchDeadLetter { id: u64 },
+    #[error("Sent {sent}, but the transfer asked for {expected}")]
+    DenomMismatch { sent: String, expected: String },
+    #[error("Protocol fees are not configured")]
+    NoFeeConfig {},
+    #[error("No fee denom is configured")]
+    NoFeeDenom {},
+    #[error("The protocol fee must be paid with exactly {expected}")]
+    InvalidFeePayment { expected: String },
+    #[error("Fee of {bps} basis points is above 100%")]
+    InvalidFeeBps { bps: u64 },
+    #[error("Referral fee of {bps} basis points is above 100% of the fee")]
+    InvalidReferrerBps { bps: u64 },
+    #[error("No referral fees to claim")]
+    NoReferralFees {},
+    #[error("Fee collectors need a weight above zero")]
+    InvalidCollectorWeight {},
+    #[error("Fee tiers must be sorted by strictly increasing min_amount")]
+    InvalidFeeTiers {},
+    #[error("Can't read {duration} as a duration like 90s, 15m or 2h")]
+    InvalidDuration { duration: String },
+    #[error(
+        "Timeout of {timeout} seconds is above the maximum of {max}, was it given in milliseconds?"
+    )]
+    TimeoutTooLong { timeout: u64, max: u64 },
+    #[error("Give either timeout or timeout_duration, not both")]
+    ConflictingTimeouts {},
+    #[error("Memo of {length} bytes is above the limit of {max}")]
+    MemoTooLong { length: u64, max: u64 },
+    #[error("Packet data of {size} bytes is above the limit of {max}")]
+    PacketTooLarge { size: u64, max: u64 },
+    #[error("No htlc is locked against {hash}")]
+    NoHtlcForHash { hash: String },
+    #[error("Several htlcs are locked against {hash}, name the one to claim")]
+    AmbiguousHtlc { hash: String },
+    #[error("Channel {id} is a portal channel of this contract")]
+    PortalChannel { id: String },
+    #[error("The stored config has no owner, the migration must name one")]
+    MissingOwner {},
+    #[error("A transfer cannot name its own sender as referrer")]
+    SelfReferral {},
+    #[error("The fee of {fee} leaves nothing of the {amount} to transfer")]
+    FeeExceedsAmount { fee: Uint128, amount: Uint128 },
+    #[error("TransferCw20 takes no funds, so the fee in {denom} cannot be attached")]
+    FeeNotAttachable { denom: String },
+    #[error("Htlc locks {amount}, less than the minimum of {min}")]
+    HtlcTooSmall { amount: Uint128, min: Uint128 },
+    #[error("Native transfer doesn't exist: {id}")]
+    NoSuchNativeTransfer { id: u64 },
 }
 
 /// Never is a placeholder to ensure we don't return any errors
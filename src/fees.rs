@@ -0,0 +1,259 @@
+use crate::amount::Amount;
+use crate::error::ContractError;
+use crate::state::{
+    increase_escrowed, FeeConfig, FeeTier, TierFee, COLLECTED_FEES, FEE_CONFIG, FEE_TIERS,
+    LIFETIME_FEES, REFERRER_FEES,
+};
+use cosmwasm_std::{
+    to_binary, Addr, Coin, CosmosMsg, Deps, Env, Order, StdResult, Storage, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+
+/// basis points are parts per 10_000
+pub const BPS_DENOMINATOR: u128 = 10_000;
+
+/// What a transfer owes the protocol
+pub struct ProtocolFee {
+    /// the amount left to bridge once the fee is taken
+    pub net: Amount,
+    /// the fee charged, if any
+    pub fee: Option<Amount>,
+    /// messages that collect the fee, when it is pulled from a cw20 allowance
+    pub messages: Vec<CosmosMsg>,
+    /// index of the fee tier applied, if the denom has a fee schedule
+    pub tier: Option<usize>,
+}
+
+/// Takes the coin paying a native flat fee out of the attached funds, leaving only the bridged coin
+pub fn take_fee_coin(
+    storage: &dyn Storage,
+    funds: &mut Vec<Coin>,
+) -> Result<Option<Coin>, ContractError> {
+    let flat = FEE_CONFIG
+        .may_load(storage)?
+        .and_then(|cfg| cfg.fee_denom)
+        .ok_or(ContractError::NoFeeDenom {})?;
+    if flat.denom.starts_with("cw20:") {
+        return Ok(None);
+    }
+    let fee_coin = funds
+        .iter()
+        .position(|c| c.denom == flat.denom)
+        .map(|idx| funds.remove(idx));
+    Ok(fee_coin)
+}
+
+/// Works out the fee for a transfer of `amount`. With `pay_in_fee_denom` the bridged amount stays
+/// whole and the configured flat fee is paid with `fee_coin` (native) or pulled from the sender's
+/// cw20 allowance, so it succeeds or fails atomically with the transfer.
+pub fn protocol_fee(
+    deps: Deps,
+    env: &Env,
+    amount: Amount,
+    sender: &Addr,
+    pay_in_fee_denom: bool,
+    fee_coin: Option<Coin>,
+) -> Result<ProtocolFee, ContractError> {
+    // treasury sends would only pay the fee back into the contract's own balance
+    if sender == &env.contract.address {
+        return Ok(ProtocolFee {
+            net: amount,
+            fee: None,
+            messages: vec![],
+            tier: None,
+        });
+    }
+    let cfg = match FEE_CONFIG.may_load(deps.storage)? {
+        Some(cfg) => cfg,
+        None if pay_in_fee_denom => return Err(ContractError::NoFeeDenom {}),
+        None => {
+            return Ok(ProtocolFee {
+                net: amount,
+                fee: None,
+                messages: vec![],
+                tier: None,
+            })
+        }
+    };
+
+    if pay_in_fee_denom {
+        let flat = cfg.fee_denom.ok_or(ContractError::NoFeeDenom {})?;
+        let fee = Amount::from_parts(flat.denom.clone(), flat.amount);
+        let mut messages = vec![];
+        match &fee {
+            Amount::Native(expected) => {
+                if fee_coin.as_ref() != Some(expected) {
+                    return Err(ContractError::InvalidFeePayment {
+                        expected: expected.to_string(),
+                    });
+                }
+            }
+            Amount::Cw20(coin) => {
+                let pull = Cw20ExecuteMsg::TransferFrom {
+                    owner: sender.to_string(),
+                    recipient: env.contract.address.to_string(),
+                    amount: coin.amount,
+                };
+                messages.push(
+                    WasmMsg::Execute {
+                        contract_addr: coin.address.clone(),
+                        msg: to_binary(&pull)?,
+                        funds: vec![],
+                    }
+                    .into(),
+                );
+            }
+        }
+        return Ok(ProtocolFee {
+            net: amount,
+            fee: Some(fee).filter(|f| !f.is_empty()),
+            messages,
+            tier: None,
+        });
+    }
+
+    let tiers = FEE_TIERS
+        .may_load(deps.storage, &amount.denom())?
+        .unwrap_or_default();
+    let tier = applied_tier(&tiers, amount.amount());
+    let fee_amount = match tier.map(|idx| &tiers[idx].fee) {
+        Some(TierFee::Flat(flat)) => *flat,
+        Some(TierFee::Bps(bps)) => amount
+            .amount()
+            .multiply_ratio(*bps as u128, BPS_DENOMINATOR),
+        None => amount
+            .amount()
+            .multiply_ratio(cfg.bps as u128, BPS_DENOMINATOR),
+    };
+    // a flat tier fee can swallow a small transfer whole
+    if !fee_amount.is_zero() && fee_amount >= amount.amount() {
+        return Err(ContractError::FeeExceedsAmount {
+            fee: fee_amount,
+            amount: amount.amount(),
+        });
+    }
+    let net = Amount::from_parts(amount.denom(), amount.amount() - fee_amount);
+    if net.is_empty() {
+        return Err(ContractError::NoFunds {});
+    }
+    Ok(ProtocolFee {
+        net,
+        fee: Some(Amount::from_parts(amount.denom(), fee_amount)).filter(|f| !f.is_empty()),
+        messages: vec![],
+        tier,
+    })
+}
+
+/// The last tier the amount reaches, tiers are sorted by min_amount
+pub fn applied_tier(tiers: &[FeeTier], amount: Uint128) -> Option<usize> {
+    tiers.iter().rposition(|t| amount >= t.min_amount)
+}
+
+pub fn accrue_fee(storage: &mut dyn Storage, fee: &Amount) -> StdResult<()> {
+    COLLECTED_FEES.update(storage, &fee.denom(), |orig| -> StdResult<_> {
+        Ok(orig.unwrap_or_default() + fee.amount())
+    })?;
+    LIFETIME_FEES.update(storage, &fee.denom(), |orig| -> StdResult<_> {
+        Ok(orig.unwrap_or_default() + fee.amount())
+    })?;
+    Ok(())
+}
+
+/// Moves the referrer's share of an accrued fee from the collected fees to the referrer.
+/// Returns the share
+pub fn credit_referrer(
+    storage: &mut dyn Storage,
+    referrer: &Addr,
+    fee: &Amount,
+) -> StdResult<Uint128> {
+    let bps = FEE_CONFIG
+        .may_load(storage)?
+        .map(|cfg| cfg.referrer_bps)
+        .unwrap_or_default();
+    let share = fee.amount().multiply_ratio(bps as u128, BPS_DENOMINATOR);
+    if share.is_zero() {
+        return Ok(share);
+    }
+    COLLECTED_FEES.update(storage, &fee.denom(), |orig| -> StdResult<_> {
+        Ok(orig.unwrap_or_default().checked_sub(share)?)
+    })?;
+    REFERRER_FEES.update(storage, (referrer, &fee.denom()), |orig| -> StdResult<_> {
+        Ok(orig.unwrap_or_default() + share)
+    })?;
+    increase_escrowed(storage, &Amount::from_parts(fee.denom(), share))?;
+    Ok(share)
+}
+
+/// Fee shares the referrer can claim, as amounts
+pub fn referrer_fees(storage: &dyn Storage, referrer: &Addr) -> StdResult<Vec<Amount>> {
+    REFERRER_FEES
+        .prefix(referrer)
+        .range(storage, None, None, Order::Ascending)
+        .map(|r| {
+            let (k, v): (Vec<u8>, Uint128) = r?;
+            Ok(Amount::from_parts(String::from_utf8(k)?, v))
+        })
+        .collect()
+}
+
+pub fn validate_fee_config(cfg: &FeeConfig) -> Result<(), ContractError> {
+    if cfg.bps as u128 > BPS_DENOMINATOR {
+        return Err(ContractError::InvalidFeeBps { bps: cfg.bps });
+    }
+    if cfg.collectors.iter().any(|c| c.weight == 0) {
+        return Err(ContractError::InvalidCollectorWeight {});
+    }
+    if cfg.referrer_bps as u128 > BPS_DENOMINATOR {
+        return Err(ContractError::InvalidReferrerBps {
+            bps: cfg.referrer_bps,
+        });
+    }
+    Ok(())
+}
+
+pub fn validate_fee_tiers(tiers: &[FeeTier]) -> Result<(), ContractError> {
+    if tiers.windows(2).any(|w| w[0].min_amount >= w[1].min_amount) {
+        return Err(ContractError::InvalidFeeTiers {});
+    }
+    for tier in tiers {
+        if let TierFee::Bps(bps) = tier.fee {
+            if bps as u128 > BPS_DENOMINATOR {
+                return Err(ContractError::InvalidFeeBps { bps });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Splits a fee among the configured collectors by weight, the first one gets the rounding dust.
+/// Without collectors the single collector gets all of it
+pub fn split_fee(cfg: &FeeConfig, fee: Uint128) -> Vec<(Addr, Uint128)> {
+    if cfg.collectors.is_empty() {
+        return vec![(cfg.collector.clone(), fee)];
+    }
+    let total: u128 = cfg.collectors.iter().map(|c| c.weight as u128).sum();
+    let mut shares: Vec<_> = cfg
+        .collectors
+        .iter()
+        .map(|c| {
+            (
+                c.address.clone(),
+                fee.multiply_ratio(c.weight as u128, total),
+            )
+        })
+        .collect();
+    let paid: Uint128 = shares.iter().map(|(_, share)| *share).sum();
+    shares[0].1 += fee - paid;
+    shares
+}
+
+/// Collected fees, as amounts
+pub fn collected_fees(storage: &dyn Storage) -> StdResult<Vec<Amount>> {
+    COLLECTED_FEES
+        .range(storage, None, None, Order::Ascending)
+        .map(|r| {
+            let (k, v): (Vec<u8>, Uint128) = r?;
+            Ok(Amount::from_parts(String::from_utf8(k)?, v))
+        })
+        .collect()
+}
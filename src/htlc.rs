@@ -0,0 +1,190 @@
+use crate::amount::Amount;
+use crate::contract::{DEFAULT_LIMIT, MAX_LIMIT};
+use crate::error::ContractError;
+use crate::ibc::send_amount;
+use crate::msg::{HtlcsResponse, LockHtlcMsg};
+use crate::schedule::validate_token;
+use crate::state::{
+    increase_escrowed, param_u64, reduce_escrowed, Htlc, HTLCS, HTLCS_BY_HASH, HTLC_COUNT,
+    PARAM_MIN_HTLC_AMOUNT,
+};
+use cosmwasm_std::{
+    attr, Addr, BlockInfo, Deps, DepsMut, Env, Order, Response, StdResult, Storage, Uint128,
+};
+use cw0::Expiration;
+use cw_storage_plus::{Bound, U64Key};
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+
+pub fn execute_lock_htlc(
+    deps: DepsMut,
+    env: Env,
+    msg: LockHtlcMsg,
+    amount: Amount,
+    sender: Addr,
+) -> Result<Response, ContractError> {
+    if amount.is_empty() {
+        return Err(ContractError::NoFunds {});
+    }
+    let hash = msg.hash.to_lowercase();
+    let valid_hash = hex::decode(&hash).map(|h| h.len() == 32).unwrap_or(false);
+    let never = matches!(msg.expires, Expiration::Never {});
+    if !valid_hash || never || msg.expires.is_expired(&env.block) {
+        return Err(ContractError::InvalidHtlc {});
+    }
+    validate_token(deps.as_ref(), env.block.time, &amount)?;
+    if let Some(min) = param_u64(deps.storage, PARAM_MIN_HTLC_AMOUNT)? {
+        if amount.amount() < Uint128::from(min) {
+            return Err(ContractError::HtlcTooSmall {
+                amount: amount.amount(),
+                min: min.into(),
+            });
+        }
+    }
+
+    // anyone may lock against a hash they have seen, so one hash can hold several htlcs
+    let id = HTLC_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+    HTLC_COUNT.save(deps.storage, &id)?;
+    let htlc = Htlc {
+        id,
+        hash,
+        sender,
+        recipient: deps.api.addr_validate(&msg.recipient)?,
+        amount,
+        expires: msg.expires,
+    };
+    HTLCS.save(deps.storage, id.into(), &htlc)?;
+    HTLCS_BY_HASH.save(deps.storage, (&htlc.hash, id.into()), &true)?;
+    increase_escrowed(deps.storage, &htlc.amount)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "lock_htlc"),
+        attr("htlc_id", id.to_string()),
+        attr("hash", &htlc.hash),
+        attr("sender", &htlc.sender),
+        attr("recipient", &htlc.recipient),
+        attr("denom", htlc.amount.denom()),
+        attr("amount", htlc.amount.amount().to_string()),
+        attr("expires", htlc.expires.to_string()),
+    ]))
+}
+
+pub fn execute_claim_htlc(
+    deps: DepsMut,
+    env: Env,
+    preimage: String,
+    id: Option<u64>,
+) -> Result<Response, ContractError> {
+    let htlc = claim_htlc(deps.storage, &env.block, &preimage, id)?;
+    // the preimage goes into the event so the counterparty can claim on the other chain
+    Ok(Response::new()
+        .add_message(send_amount(htlc.amount, htlc.recipient.to_string()))
+        .add_attributes(vec![
+            attr("action", "claim_htlc"),
+            attr("htlc_id", htlc.id.to_string()),
+            attr("hash", htlc.hash),
+            attr("recipient", htlc.recipient),
+            attr("preimage", preimage),
+        ]))
+}
+
+pub fn execute_refund_htlc(deps: DepsMut, env: Env, id: u64) -> Result<Response, ContractError> {
+    let htlc = load_htlc(deps.storage, id)?;
+    if !htlc.expires.is_expired(&env.block) {
+        return Err(ContractError::HtlcNotExpired {});
+    }
+    remove_htlc(deps.storage, &htlc)?;
+
+    Ok(Response::new()
+        .add_message(send_amount(htlc.amount, htlc.sender.to_string()))
+        .add_attributes(vec![
+            attr("action", "refund_htlc"),
+            attr("htlc_id", id.to_string()),
+            attr("hash", htlc.hash),
+            attr("sender", htlc.sender),
+        ]))
+}
+
+/// Removes the unexpired htlc locked against the hash of the hex encoded preimage. Without an id
+/// the hash has to match exactly one htlc, expired or not, so the lookup never reads more than
+/// two entries. The caller releases the funds to htlc.recipient
+pub(crate) fn claim_htlc(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    preimage: &str,
+    id: Option<u64>,
+) -> Result<Htlc, ContractError> {
+    let preimage = hex::decode(preimage).map_err(|_| ContractError::InvalidHtlc {})?;
+    let hash = hex::encode(Sha256::digest(&preimage));
+    let htlc = match id {
+        Some(id) => {
+            let htlc = load_htlc(storage, id)?;
+            if htlc.hash != hash {
+                return Err(ContractError::NoHtlcForHash { hash });
+            }
+            if htlc.expires.is_expired(block) {
+                return Err(ContractError::HtlcExpired {});
+            }
+            htlc
+        }
+        None => {
+            // anyone can lock against a known hash, so stop at the second match
+            let mut locked = htlcs_by_hash(storage, &hash, None, 2)?.into_iter();
+            match (locked.next(), locked.next()) {
+                (Some(htlc), None) if htlc.expires.is_expired(block) => {
+                    return Err(ContractError::HtlcExpired {})
+                }
+                (Some(htlc), None) => htlc,
+                (Some(_), Some(_)) => return Err(ContractError::AmbiguousHtlc { hash }),
+                (None, _) => return Err(ContractError::NoHtlcForHash { hash }),
+            }
+        }
+    };
+    remove_htlc(storage, &htlc)?;
+    Ok(htlc)
+}
+
+fn load_htlc(storage: &dyn Storage, id: u64) -> Result<Htlc, ContractError> {
+    HTLCS
+        .may_load(storage, id.into())?
+        .ok_or(ContractError::NoSuchHtlc { id })
+}
+
+fn remove_htlc(storage: &mut dyn Storage, htlc: &Htlc) -> StdResult<()> {
+    HTLCS_BY_HASH.remove(storage, (&htlc.hash, htlc.id.into()));
+    HTLCS.remove(storage, htlc.id.into());
+    reduce_escrowed(storage, &htlc.amount)
+}
+
+fn htlcs_by_hash(
+    storage: &dyn Storage,
+    hash: &str,
+    start_after: Option<u64>,
+    limit: usize,
+) -> StdResult<Vec<Htlc>> {
+    let start = start_after.map(|id| Bound::exclusive(U64Key::new(id)));
+    HTLCS_BY_HASH
+        .prefix(hash)
+        .keys(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|k| {
+            let id: [u8; 8] = k.as_slice().try_into().unwrap();
+            HTLCS.load(storage, u64::from_be_bytes(id).into())
+        })
+        .collect()
+}
+
+pub fn query_htlc(deps: Deps, id: u64) -> StdResult<Htlc> {
+    HTLCS.load(deps.storage, id.into())
+}
+
+pub fn query_htlcs_by_hash(
+    deps: Deps,
+    hash: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<HtlcsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let htlcs = htlcs_by_hash(deps.storage, &hash.to_lowercase(), start_after, limit)?;
+    Ok(HtlcsResponse { htlcs })
+}
@@ -1,14 +1,20 @@
 use crate::amount::Amount;
+use crate::contract::record_native_sequence;
 use crate::error::{ContractError, Never};
+use crate::htlc::claim_htlc;
+use crate::pull::finish_pull;
 use crate::state::{
-    increase_channel_balance, reduce_channel_balance, undo_reduce_channel_balance, ChannelInfo,
-    ReplyArgs, CHANNEL_INFO, REPLY_ARGS,
+    increase_channel_balance, reduce_channel_balance, save_dead_letter, settle_transfer_record,
+    undo_reduce_channel_balance, update_metrics, AckFormat, ChannelInfo, DeadLetter, HopReceiver,
+    RefundArgs, ReplyArgs, Route, RouteHop, TransferStatus, CHANNEL_CONFIG, CHANNEL_INFO,
+    REFUND_ARGS, REPLY_ARGS,
 };
 use cosmwasm_std::{
-    attr, entry_point, from_binary, to_binary, BankMsg, Binary, ContractResult, CosmosMsg, DepsMut,
-    Env, IbcBasicResponse, IbcChannel, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg,
-    IbcEndpoint, IbcOrder, IbcPacket, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg,
-    IbcReceiveResponse, Reply, Response, SubMsg, Uint128, WasmMsg,
+    attr, entry_point, from_binary, from_slice, to_binary, to_vec, BankMsg, Binary, ContractResult,
+    CosmosMsg, DepsMut, Env, Event, IbcBasicResponse, IbcChannel, IbcChannelCloseMsg,
+    IbcChannelConnectMsg, IbcChannelOpenMsg, IbcEndpoint, IbcOrder, IbcPacket, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, Reply, Response, StdError,
+    StdResult, SubMsg, Uint128, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
 use schemars::JsonSchema;
@@ -19,23 +25,28 @@ pub const ICS20_ORDERING: IbcOrder = IbcOrder::Unordered;
 
 /// The format for sending an ics20 packet.
 /// Proto defined here: https://github.com/cosmos/cosmos-sdk/blob/v0.42.0/proto/ibc/applications/transfer/v1/transfer.proto#L11-L20
-/// This is compatible with the JSON serialization
+/// This is compatible with the JSON serialization. ibc-go sorts the keys and omits empty fields,
+/// keep the fields in alphabetical order so our packets encode byte for byte the same.
+/// Packets from before this order had memo after v and v as null when unset, they still decode
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug, Default)]
 pub struct Ics20Packet {
     /// amount of tokens to transfer is encoded as a string, but limited to u64 max
     pub amount: Uint128,
     /// the token denomination to be transferred
     pub denom: String,
+    /// free-form data for the receiving chain, see PacketMemo for what we act on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
     /// the recipient address on the destination chain
     pub receiver: String,
     /// the sender address
     pub sender: String,
-    /// used only by us to control ack handling
+    /// set to 2 by older builds, which added to the channel balance before sending. Packets
+    /// sent now leave it out like ibc-go and are told apart by their transfer record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub v: Option<u32>,
 }
 
-const V2: u32 = 2;
-
 impl Ics20Packet {
     pub fn new<T: Into<String>>(amount: Uint128, denom: T, sender: &str, receiver: &str) -> Self {
         Ics20Packet {
@@ -43,10 +54,16 @@ impl Ics20Packet {
             amount,
             sender: sender.to_string(),
             receiver: receiver.to_string(),
-            v: Some(V2),
+            v: None,
+            memo: None,
         }
     }
 
+    pub fn with_memo(mut self, memo: Option<String>) -> Self {
+        self.memo = memo;
+        self
+    }
+
     pub fn validate(&self) -> Result<(), ContractError> {
         if self.amount.u128() > (u64::MAX as u128) {
             Err(ContractError::AmountOverflow {})
@@ -56,6 +73,123 @@ impl Ics20Packet {
     }
 }
 
+/// Event types of the ibc-go transfer module, so indexers that know it pick up portal packets
+pub const EVENT_TRANSFER: &str = "ibc_transfer";
+pub const EVENT_PACKET: &str = "fungible_token_packet";
+pub const EVENT_TIMEOUT: &str = "timeout";
+
+/// An event with the transfer module's packet attributes, plus the core ibc ones identifying
+/// the packet
+pub(crate) fn packet_event(ty: &str, msg: &Ics20Packet, packet: &IbcPacket) -> Event {
+    Event::new(ty)
+        .add_attribute("sender", &msg.sender)
+        .add_attribute("receiver", &msg.receiver)
+        .add_attribute("denom", &msg.denom)
+        .add_attribute("amount", msg.amount.to_string())
+        .add_attribute("memo", msg.memo.clone().unwrap_or_default())
+        .add_attribute(
+            "packet_data",
+            String::from_utf8_lossy(packet.data.as_slice()),
+        )
+        .add_attribute("packet_sequence", packet.sequence.to_string())
+        .add_attribute("packet_src_channel", &packet.src.channel_id)
+        .add_attribute("packet_dst_channel", &packet.dest.channel_id)
+        .add_attribute(
+            "packet_timeout_timestamp",
+            packet
+                .timeout
+                .timestamp()
+                .map(|t| t.nanos())
+                .unwrap_or_default()
+                .to_string(),
+        )
+}
+
+/// Why an outgoing packet's escrow is returned
+enum RefundCause<'a> {
+    Timeout,
+    ErrorAck { ack: &'a Binary, error: String },
+}
+
+/// Splits an error ack from ibc-go ("ABCI code: 5: error handling packet: ...") into the code
+/// and message. Other formats have no code, the message is the whole error
+pub fn decode_ack_error(error: &str) -> (Option<u32>, String) {
+    let decoded = error.strip_prefix("ABCI code: ").and_then(|rest| {
+        let (code, message) = rest.split_once(": ")?;
+        Some((code.parse().ok()?, message.to_string()))
+    });
+    match decoded {
+        Some((code, message)) => (Some(code), message),
+        None => (None, error.to_string()),
+    }
+}
+
+/// The instructions we understand in the memo of an incoming packet, as json.
+/// Unknown fields are ignored so memos meant for other middleware pass through.
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug, Default)]
+pub struct PacketMemo {
+    pub htlc: Option<HtlcMemo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct HtlcMemo {
+    /// hex encoded preimage, releases the local htlc locked against its hash
+    pub preimage: String,
+    /// which htlc to release when several are locked against the hash
+    #[serde(default)]
+    pub id: Option<u64>,
+}
+
+/// Packet forward middleware memo, see
+/// https://github.com/cosmos/ibc-apps/tree/main/middleware/packet-forward-middleware
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ForwardMemo {
+    pub forward: Forward,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Forward {
+    pub receiver: String,
+    pub port: String,
+    pub channel: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<Box<ForwardMemo>>,
+}
+
+/// The packet receiver and memo that carry a transfer along the route to `receiver`
+pub fn route_memo(route: &Route, receiver: &str) -> StdResult<(String, String)> {
+    let hop_receiver = |hop: &RouteHop| match &hop.receiver {
+        HopReceiver::Placeholder {} => "pfm".to_string(),
+        HopReceiver::Address { address } => address.clone(),
+    };
+    // built from the last hop outward, each forward names the receiver of the next chain
+    let mut next: Option<Box<ForwardMemo>> = None;
+    let mut next_receiver = receiver.to_string();
+    for hop in route.hops.iter().rev() {
+        let memo = ForwardMemo {
+            forward: Forward {
+                receiver: next_receiver,
+                port: hop.port.clone().unwrap_or_else(|| "transfer".to_string()),
+                channel: hop.channel.clone(),
+                timeout: hop.timeout.clone(),
+                retries: hop.retries,
+                next,
+            },
+        };
+        next = Some(Box::new(memo));
+        next_receiver = hop_receiver(hop);
+    }
+    let memo = match next {
+        Some(memo) => String::from_utf8(to_vec(&memo)?)?,
+        None => return Err(StdError::generic_err("route without hops")),
+    };
+    Ok((next_receiver, memo))
+}
+
 /// This is a generic ICS acknowledgement format.
 /// Proto defined here: https://github.com/cosmos/cosmos-sdk/blob/v0.42.0/proto/ibc/core/channel/v1/channel.proto#L141-L147
 /// This is compatible with the JSON serialization
@@ -66,8 +200,8 @@ pub enum Ics20Ack {
     Error(String),
 }
 // create a serialized success message
-fn ack_success() -> Binary {
-    let res = Ics20Ack::Result(b"1".into());
+pub(crate) fn ack_success() -> Binary {
+    let res = Ics20Ack::Result(vec![1].into());
     to_binary(&res).unwrap()
 }
 
@@ -76,11 +210,66 @@ fn ack_fail(err: String) -> Binary {
     let res = Ics20Ack::Error(err);
     to_binary(&res).unwrap()
 }
+/// Reads an acknowledgement in the format the counterparty uses
+pub fn parse_ack(format: &AckFormat, ack: &Binary) -> StdResult<Ics20Ack> {
+    match format {
+        AckFormat::Ics20Json => from_binary(ack),
+        AckFormat::Protobuf => parse_proto_ack(ack.as_slice()),
+        AckFormat::Exact { success, .. } if success == ack => Ok(Ics20Ack::Result(ack.clone())),
+        AckFormat::Exact { error_prefix, .. } if ack.as_slice().starts_with(error_prefix) => Ok(
+            Ics20Ack::Error(String::from_utf8_lossy(ack.as_slice()).into_owned()),
+        ),
+        AckFormat::Exact { .. } => Err(StdError::parse_err(
+            "Acknowledgement",
+            "neither the success ack nor an error",
+        )),
+    }
+}
+
+// the oneof holds a single length-delimited field, anything else is not an acknowledgement
+fn parse_proto_ack(mut data: &[u8]) -> StdResult<Ics20Ack> {
+    const RESULT_TAG: u64 = 21 << 3 | 2;
+    const ERROR_TAG: u64 = 22 << 3 | 2;
+    let tag = read_varint(&mut data)?;
+    let len = read_varint(&mut data)? as usize;
+    if data.len() != len {
+        return Err(StdError::parse_err(
+            "Acknowledgement",
+            "invalid field length",
+        ));
+    }
+    match tag {
+        RESULT_TAG => Ok(Ics20Ack::Result(data.into())),
+        ERROR_TAG => String::from_utf8(data.to_vec())
+            .map(Ics20Ack::Error)
+            .map_err(|_| StdError::parse_err("Acknowledgement", "error is not utf-8")),
+        _ => Err(StdError::parse_err("Acknowledgement", "unexpected field")),
+    }
+}
+
+fn read_varint(data: &mut &[u8]) -> StdResult<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = data
+            .split_first()
+            .ok_or_else(|| StdError::parse_err("Acknowledgement", "truncated varint"))?;
+        *data = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(StdError::parse_err("Acknowledgement", "varint too long"))
+}
+
 const RECEIVE_ID: u64 = 1337;
 const ACK_FAILURE_ID: u64 = 0xfa17;
+const HTLC_RELEASE_ID: u64 = 0x4c7c;
+pub(crate) const CW20_PULL_ID: u64 = 0x9011;
+pub(crate) const NATIVE_TRANSFER_ID: u64 = 0x7a45;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
     match reply.id {
         RECEIVE_ID => match reply.result {
             ContractResult::Ok(_) => Ok(Response::new()),
@@ -104,14 +293,40 @@ pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, Contrac
                     &reply_args.denom,
                     reply_args.amount,
                 )?;
+                // the packet was counted as received before the delivery bounced
+                update_metrics(deps.storage, |m| {
+                    m.packets_received = m.packets_received.saturating_sub(1)
+                })?;
 
                 Ok(Response::new().set_data(ack_fail(err)))
             }
         },
-        ACK_FAILURE_ID => match reply.result {
+        ACK_FAILURE_ID | HTLC_RELEASE_ID => match reply.result {
             ContractResult::Ok(_) => Ok(Response::new()),
-            ContractResult::Err(err) => Ok(Response::new().set_data(ack_fail(err))),
+            ContractResult::Err(err) => {
+                // The refund (or htlc release) bounced (eg. the cw20 contract no longer accepts transfers). Park it
+                // in the dead-letter store for the owner rather than leaving the funds untracked.
+                let refund_args = REFUND_ARGS.load(deps.storage)?;
+                if reply.id == ACK_FAILURE_ID {
+                    update_metrics(deps.storage, |m| {
+                        m.packets_refunded = m.packets_refunded.saturating_sub(1)
+                    })?;
+                }
+                let letter = save_dead_letter(deps.storage, refund_args, err, env.block.height)?;
+                Ok(Response::new()
+                    .add_event(dead_letter_event(&letter))
+                    .add_attribute("action", "dead_letter")
+                    .add_attribute("dead_letter_id", letter.id.to_string())
+                    .add_attribute("error", letter.reason))
+            }
         },
+        // reply_on_success, a failed pull reverts the whole transfer
+        CW20_PULL_ID => finish_pull(deps, env),
+        // reply_on_success, the transfer module's send_packet event carries the sequence
+        NATIVE_TRANSFER_ID => {
+            let res = reply.result.into_result().map_err(StdError::generic_err)?;
+            record_native_sequence(deps.storage, &res.events)
+        }
         _ => Err(ContractError::UnknownReplyId { id: reply.id }),
     }
 }
@@ -144,6 +359,7 @@ pub fn ibc_channel_connect(
         connection_id: channel.connection_id,
     };
     CHANNEL_INFO.save(deps.storage, &info.id, &info)?;
+    update_metrics(deps.storage, |m| m.channels_opened += 1)?;
 
     Ok(IbcBasicResponse::default())
 }
@@ -163,16 +379,24 @@ pub fn ibc_channel_close(
 /// check if success or failure and update balance, or return funds
 pub fn ibc_packet_ack(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     msg: IbcPacketAckMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
     // Design decision: should we trap error like in receive?
     // TODO: unsure... as it is now a failed ack handling would revert the tx and would be
     // retried again and again. is that good?
-    let ics20msg: Ics20Ack = from_binary(&msg.acknowledgement.data)?;
+    let ack = msg.acknowledgement.data;
+    let format = CHANNEL_CONFIG
+        .may_load(deps.storage, &msg.original_packet.src.channel_id)?
+        .unwrap_or_default()
+        .ack_format;
+    let ics20msg = parse_ack(&format, &ack)?;
     match ics20msg {
-        Ics20Ack::Result(_) => on_packet_success(deps, msg.original_packet),
-        Ics20Ack::Error(err) => on_packet_failure(deps, msg.original_packet, err),
+        Ics20Ack::Result(_) => on_packet_success(deps, msg.original_packet, &ack),
+        Ics20Ack::Error(error) => {
+            let cause = RefundCause::ErrorAck { ack: &ack, error };
+            on_packet_failure(deps, env, msg.original_packet, cause)
+        }
     }
 }
 
@@ -180,12 +404,13 @@ pub fn ibc_packet_ack(
 /// return fund to original sender (same as failure in ibc_packet_ack)
 pub fn ibc_packet_timeout(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     msg: IbcPacketTimeoutMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
     // TODO: trap error like in receive? (same question as ack above)
     let packet = msg.packet;
-    on_packet_failure(deps, packet, "timeout".to_string())
+    update_metrics(deps.storage, |m| m.packets_timed_out += 1)?;
+    on_packet_failure(deps, env, packet, RefundCause::Timeout)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -193,25 +418,33 @@ pub fn ibc_packet_timeout(
 /// We should not return an error if possible, but rather an acknowledgement of failure
 pub fn ibc_packet_receive(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     msg: IbcPacketReceiveMsg,
 ) -> Result<IbcReceiveResponse, Never> {
     let packet = msg.packet;
 
-    do_ibc_packet_receive(deps, &packet).or_else(|err| {
-        Ok(IbcReceiveResponse::new()
+    do_ibc_packet_receive(deps, env, &packet).or_else(|err| {
+        let mut res = IbcReceiveResponse::new()
             .set_ack(ack_fail(err.to_string()))
             .add_attributes(vec![
                 attr("action", "receive"),
                 attr("success", "false"),
                 attr("error", err.to_string()),
-            ]))
+            ]);
+        if let Ok(msg) = from_binary::<Ics20Packet>(&packet.data) {
+            let event = packet_event(EVENT_PACKET, &msg, &packet)
+                .add_attribute("success", "false")
+                .add_attribute("error", err.to_string());
+            res = res.add_event(event);
+        }
+        Ok(res)
     })
 }
 
 // this does the work of ibc_packet_receive, we wrap it to turn errors into acknowledgements
 fn do_ibc_packet_receive(
     deps: DepsMut,
+    env: Env,
     packet: &IbcPacket,
 ) -> Result<IbcReceiveResponse, ContractError> {
     let msg: Ics20Packet = from_binary(&packet.data)?;
@@ -223,6 +456,7 @@ fn do_ibc_packet_receive(
 
     // make sure we have enough balance for this
     reduce_channel_balance(deps.storage, &channel, denom, msg.amount)?;
+    update_metrics(deps.storage, |m| m.packets_received += 1)?;
 
     // we need to save the data to update the balances in reply
     let reply_args = ReplyArgs {
@@ -238,16 +472,45 @@ fn do_ibc_packet_receive(
     let submsg = SubMsg::reply_on_error(send, RECEIVE_ID);
     // submsg.gas_limit = gas_limit;
 
-    let res = IbcReceiveResponse::new()
+    let event = packet_event(EVENT_PACKET, &msg, packet).add_attribute("success", "true");
+    let mut res = IbcReceiveResponse::new()
         .set_ack(ack_success())
         .add_submessage(submsg)
+        .add_event(event)
         .add_attribute("action", "receive")
-        .add_attribute("sender", msg.sender)
-        .add_attribute("receiver", msg.receiver)
+        .add_attribute("sender", &msg.sender)
+        .add_attribute("receiver", &msg.receiver)
         .add_attribute("denom", denom)
         .add_attribute("amount", msg.amount)
         .add_attribute("success", "true");
 
+    // a revealed preimage releases the matching local htlc. Whatever goes wrong with that
+    // must not fail the transfer itself
+    let memo: Option<PacketMemo> = msg
+        .memo
+        .as_deref()
+        .and_then(|m| from_slice(m.as_bytes()).ok());
+    if let Some(HtlcMemo { preimage, id }) = memo.and_then(|m| m.htlc) {
+        match claim_htlc(deps.storage, &env.block, &preimage, id) {
+            Ok(htlc) => {
+                // a bounced release is parked as a dead letter addressed to the recipient
+                let refund_args = RefundArgs {
+                    channel: packet.dest.channel_id.clone(),
+                    amount: htlc.amount.clone(),
+                    sender: htlc.recipient.to_string(),
+                    receiver: htlc.sender.to_string(),
+                };
+                REFUND_ARGS.save(deps.storage, &refund_args)?;
+                let release = send_amount(htlc.amount, htlc.recipient.to_string());
+                res = res
+                    .add_submessage(SubMsg::reply_on_error(release, HTLC_RELEASE_ID))
+                    .add_attribute("htlc_claimed", htlc.id.to_string())
+                    .add_attribute("preimage", preimage);
+            }
+            Err(err) => res = res.add_attribute("htlc_error", err.to_string()),
+        }
+    }
+
     Ok(res)
 }
 
@@ -299,17 +562,28 @@ fn enforce_order_and_version(
 }
 
 // update the balance stored on this (channel, denom) index
-fn on_packet_success(deps: DepsMut, packet: IbcPacket) -> Result<IbcBasicResponse, ContractError> {
+fn on_packet_success(
+    deps: DepsMut,
+    packet: IbcPacket,
+    ack: &Binary,
+) -> Result<IbcBasicResponse, ContractError> {
     let msg: Ics20Packet = from_binary(&packet.data)?;
 
+    let transfer_id = settle_transfer_record(
+        deps.storage,
+        &packet.src.channel_id,
+        &packet.data,
+        TransferStatus::Delivered,
+    )?;
     // if this was for an older (pre-v2) packet we send continue with old behavior
     // (this is needed for transitioning on a system with pending packet)
-    if msg.v.is_none() {
+    if msg.v.is_none() && transfer_id.is_none() {
         increase_channel_balance(deps.storage, &packet.src.channel_id, &msg.denom, msg.amount)?;
     }
+    update_metrics(deps.storage, |m| m.packets_acked += 1)?;
 
     // similar event messages like ibctransfer module
-    let attributes = vec![
+    let mut attributes = vec![
         attr("action", "acknowledge"),
         attr("sender", &msg.sender),
         attr("receiver", &msg.receiver),
@@ -317,32 +591,110 @@ fn on_packet_success(deps: DepsMut, packet: IbcPacket) -> Result<IbcBasicRespons
         attr("amount", msg.amount),
         attr("success", "true"),
     ];
+    if let Some(id) = transfer_id {
+        attributes.push(attr("transfer_id", id.to_string()));
+    }
+    let event = packet_event(EVENT_PACKET, &msg, &packet)
+        .add_attribute("acknowledgement", String::from_utf8_lossy(ack.as_slice()))
+        .add_attribute("success", "true");
 
-    Ok(IbcBasicResponse::new().add_attributes(attributes))
+    Ok(IbcBasicResponse::new()
+        .add_attributes(attributes)
+        .add_event(event))
 }
 
 // return the tokens to sender
 fn on_packet_failure(
     deps: DepsMut,
+    env: Env,
     packet: IbcPacket,
-    err: String,
+    cause: RefundCause,
 ) -> Result<IbcBasicResponse, ContractError> {
     let msg: Ics20Packet = from_binary(&packet.data)?;
-
-    // undo the balance update (but not for pre-v2/None packets which didn't add before sending)
-    if msg.v.is_some() {
+    // one event in the transfer module's shape, and one saying why we refund so relayer
+    // problems (timeouts) can be told apart from the counterparty rejecting the packet.
+    // The refund is only attempted here, a dead_letter event follows if it bounces
+    let refund = Event::new("refund")
+        .add_attribute("refund_receiver", &msg.sender)
+        .add_attribute("refund_denom", &msg.denom)
+        .add_attribute("refund_amount", msg.amount.to_string())
+        .add_attribute("packet_sequence", packet.sequence.to_string())
+        .add_attribute("packet_src_channel", &packet.src.channel_id);
+    let (event, refund, err) = match cause {
+        RefundCause::Timeout => (
+            packet_event(EVENT_TIMEOUT, &msg, &packet),
+            refund.add_attribute("cause", "timeout"),
+            "timeout".to_string(),
+        ),
+        RefundCause::ErrorAck { ack, error } => {
+            let (code, message) = decode_ack_error(&error);
+            let event = packet_event(EVENT_PACKET, &msg, &packet)
+                .add_attribute("acknowledgement", String::from_utf8_lossy(ack.as_slice()))
+                .add_attribute("success", "false")
+                .add_attribute("error", &error);
+            let mut refund = refund
+                .add_attribute("cause", "error_ack")
+                .add_attribute("error_message", message);
+            if let Some(code) = code {
+                refund = refund.add_attribute("error_code", code.to_string());
+            }
+            (event, refund, error)
+        }
+    };
+    let event = event
+        .add_attribute("refund_receiver", &msg.sender)
+        .add_attribute("refund_denom", &msg.denom)
+        .add_attribute("refund_amount", msg.amount.to_string());
+
+    let transfer_id = settle_transfer_record(
+        deps.storage,
+        &packet.src.channel_id,
+        &packet.data,
+        TransferStatus::Refunded,
+    )?;
+
+    // undo the balance update (but not for pre-v2 packets which didn't add before sending)
+    if msg.v.is_some() || transfer_id.is_some() {
         reduce_channel_balance(deps.storage, &packet.src.channel_id, &msg.denom, msg.amount)?;
     }
 
     let to_send = Amount::from_parts(msg.denom.clone(), msg.amount);
-    // let gas_limit = check_gas_limit(deps.as_ref(), &to_send)?;
-    let send = send_amount(to_send, msg.sender.clone());
-    let submsg = SubMsg::reply_on_error(send, ACK_FAILURE_ID);
-    // submsg.gas_limit = gas_limit;
+    let refund_args = RefundArgs {
+        channel: packet.src.channel_id.clone(),
+        amount: to_send.clone(),
+        sender: msg.sender.clone(),
+        receiver: msg.receiver.clone(),
+    };
+
+    let mut res = IbcBasicResponse::new().add_event(event).add_event(refund);
+    if let Some(id) = transfer_id {
+        res = res.add_attribute("transfer_id", id.to_string());
+    }
+    // some refunds can never succeed, don't even try those
+    let undeliverable = match deps.api.addr_validate(&msg.sender) {
+        Ok(addr) if addr == env.contract.address => Some("refund recipient is the contract itself"),
+        Ok(_) => None,
+        Err(_) => Some("invalid refund recipient"),
+    };
+    if let Some(reason) = undeliverable {
+        let letter = save_dead_letter(deps.storage, refund_args, reason.into(), env.block.height)?;
+        res = res
+            .add_event(dead_letter_event(&letter))
+            .add_attribute("dead_letter_id", letter.id.to_string());
+    } else {
+        // taken back in reply if the refund bounces
+        update_metrics(deps.storage, |m| m.packets_refunded += 1)?;
+        // we need to save the data to record a dead letter in reply
+        REFUND_ARGS.save(deps.storage, &refund_args)?;
+        // let gas_limit = check_gas_limit(deps.as_ref(), &to_send)?;
+        let send = send_amount(to_send, msg.sender.clone());
+        let submsg = SubMsg::reply_on_error(send, ACK_FAILURE_ID);
+        // submsg.gas_limit = gas_limit;
+        res = res.add_submessage(submsg);
+    }
 
     // similar event messages like ibctransfer module
-    let res = IbcBasicResponse::new()
-        .add_submessage(submsg)
+    let res = res
         .add_attribute("action", "acknowledge")
         .add_attribute("sender", msg.sender)
         .add_attribute("receiver", msg.receiver)
@@ -354,7 +706,17 @@ fn on_packet_failure(
     Ok(res)
 }
 
-fn send_amount(amount: Amount, recipient: String) -> CosmosMsg {
+// marks a refund or htlc release that did not go out and waits for the owner
+fn dead_letter_event(letter: &DeadLetter) -> Event {
+    Event::new("dead_letter")
+        .add_attribute("dead_letter_id", letter.id.to_string())
+        .add_attribute("refund_receiver", &letter.sender)
+        .add_attribute("refund_denom", letter.amount.denom())
+        .add_attribute("refund_amount", letter.amount.amount().to_string())
+        .add_attribute("error", &letter.reason)
+}
+
+pub(crate) fn send_amount(amount: Amount, recipient: String) -> CosmosMsg {
     match amount {
         Amount::Native(coin) => BankMsg::Send {
             to_address: recipient,
@@ -1,9 +1,20 @@
+pub mod address;
 pub mod amount;
+pub mod conditional;
+#[cfg(feature = "conformance")]
+pub mod conformance;
 pub mod contract;
+pub mod deposit;
 mod error;
+pub mod fees;
+pub mod htlc;
 pub mod ibc;
 pub mod msg;
+pub mod pull;
+pub mod schedule;
 pub mod state;
+pub mod stream;
+pub mod verifier;
 
 #[cfg(test)]
-mod testing;
\ No newline at end of file
+mod testing;
@@ -1,5 +1,11 @@
 use crate::amount::Amount;
-use crate::state::ChannelInfo;
+use crate::state::{
+    AckFormat, ChannelInfo, ChannelMode, ConfigVersion, DeadLetter, Deposit, FeeConfig, FeeTier,
+    FlatFee, Htlc, NativeTransfer, ParamValue, PauseSource, Route, ScheduleEnd, ScheduledTransfer,
+    TokenMetadata, TokenStats, TransferRecord, TreasuryEntry, WhitelistVerifier,
+};
+use cosmwasm_std::{Binary, Timestamp, Uint128};
+use cw0::Expiration;
 use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -11,6 +17,13 @@ pub struct InitMsg {
     /// initial allowlist - all cw20 tokens we will send must be previously allowed by governance
     pub whitelist: Vec<String>, //cw20 contract list
 }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    /// Required when migrating from a version whose config has no owner, replaces the owner
+    /// otherwise
+    pub owner: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
@@ -18,10 +31,337 @@ pub enum ExecuteMsg {
     Receive(Cw20ReceiveMsg),
     /// This allows us to transfer *exactly one* native token
     Transfer(TransferMsg),
+    /// Pull a cw20 from the sender's allowance and transfer the amount the contract received,
+    /// for tokens that take a tax on every transfer. Takes no funds, so a fee in the fee denom
+    /// can only be paid when that is a cw20
+    TransferCw20 {
+        contract: String,
+        amount: Uint128,
+        msg: TransferMsg,
+    },
+    /// Fund a recurring transfer with *exactly one* native token
+    ScheduleTransfer(ScheduleMsg),
+    /// Send every due scheduled transfer, up to limit. Callable by anyone
+    RunScheduled { limit: Option<u32> },
+    /// Stream *exactly one* native token to the remote address over a duration
+    CreateStream(StreamMsg),
+    /// Send what every active stream released since its last packet, up to limit streams
+    /// after start_after. Callable by anyone
+    TickStreams {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Stop releasing funds until resumed. Only callable by the stream's sender
+    PauseStream { id: u64 },
+    /// Continue a paused stream, its end moves back by the time spent paused.
+    /// Only callable by the stream's sender
+    ResumeStream { id: u64 },
+    /// Stop a stream, sending what vested since the last tick and refunding the rest. If that
+    /// transfer is rejected everything left is refunded. Only callable by its sender
+    CancelStream { id: u64 },
+    /// Escrow *exactly one* native token until the oracle reports the condition met
+    CreateConditional(ConditionalMsg),
+    /// Send a conditional transfer if its oracle reports the condition met. Callable by anyone
+    TriggerConditional { id: u64 },
+    /// Return an expired conditional transfer to its sender. Callable by anyone
+    ReclaimConditional { id: u64 },
+    /// Hold *exactly one* native token until released. The deposit id is in the response
+    Deposit { operator: Option<String> },
+    /// Change who besides the depositor may release the deposit, or clear it with None.
+    /// Only callable by the depositor
+    SetDepositOperator { id: u64, operator: Option<String> },
+    /// Send a deposit as a transfer from its depositor. Only callable by the depositor or the
+    /// deposit's operator
+    Release { id: u64, msg: TransferMsg },
+    /// Refund a deposit that has not been released. Only callable by the depositor
+    CancelDeposit { id: u64 },
+    /// Register a condition adapter under a name, replacing any previous one.
+    /// Only callable by the owner
+    RegisterOracle { name: String, address: String },
+    /// Only callable by the owner
+    RemoveOracle { name: String },
+    /// Register a multi-hop route transfers can name. Only callable by the owner
+    SetRoute { name: String, route: Route },
+    /// Only callable by the owner
+    RemoveRoute { name: String },
+    /// Lock *exactly one* native token until the preimage of the hash is revealed or it expires
+    LockHtlc(LockHtlcMsg),
+    /// Release a hash-time-locked escrow to its recipient. Callable by anyone who knows the preimage.
+    /// The id is only needed when several htlcs, expired or not, are locked against the same hash
+    ClaimHtlc { preimage: String, id: Option<u64> },
+    /// Return an expired hash-time-locked escrow to its sender. Callable by anyone
+    RefundHtlc { id: u64 },
+    /// Stop a scheduled transfer and refund the escrow left. Only callable by its sender
+    CancelScheduled { id: u64 },
+    /// Change the terms of a scheduled transfer. Fields not specified are kept.
+    /// Only callable by its sender
+    UpdateScheduled {
+        id: u64,
+        remote_address: Option<String>,
+        amount: Option<Uint128>,
+        interval: Option<u64>,
+        end: Option<ScheduleEnd>,
+        timeout: Option<u64>,
+        /// Instead of timeout, how long the packets live as a duration like "2h"
+        timeout_duration: Option<String>,
+    },
+    /// Choose how transfers over the channel are dispatched. Only callable by the owner
+    SetChannelMode { channel: String, mode: ChannelMode },
+    /// Set the bech32 prefix VerifyRemoteAddress expects for the channel, or clear it with None.
+    /// Only callable by the owner
+    SetRemotePrefix {
+        channel: String,
+        prefix: Option<String>,
+    },
+    /// Set how acknowledgements from the channel's counterparty are read.
+    /// Only callable by the owner
+    SetAckFormat { channel: String, format: AckFormat },
+    /// Change settings from instantiation. Fields not specified are kept.
+    /// Only callable by the owner
+    UpdateConfig {
+        default_timeout: Option<u64>,
+        owner: Option<String>,
+    },
+    /// Store a typed parameter under the given key, replacing any previous value.
+    /// Only callable by the owner
+    SetParam { key: String, value: ParamValue },
+    /// Remove a parameter so readers fall back to their default. Only callable by the owner
+    UnsetParam { key: String },
+    /// Halt outgoing transfers. Owner pauses last until Unpause; guardian pauses
+    /// lift automatically once the configured guardian pause duration has passed
+    Pause {},
+    /// Resume outgoing transfers. Only callable by the owner
+    Unpause {},
+    /// Settle a refund that could not be delivered. Only callable by the owner
+    ResolveDeadLetter {
+        id: u64,
+        resolution: DeadLetterResolution,
+    },
+    /// Let an alias like "usdc" stand for a full denom (native or "cw20:<address>") on the channel.
+    /// Aliases are only resolved for TransferMsg.denom and ResolveDenom, the attached funds and
+    /// the packets always use the full denom. Only callable by the owner
+    SetDenomAlias {
+        channel: String,
+        alias: String,
+        denom: String,
+    },
+    /// Only callable by the owner
+    RemoveDenomAlias { channel: String, alias: String },
+    /// Charge a protocol fee on transfers, or stop charging with None, which first pays out
+    /// the collected fees. Only callable by the owner
+    SetFeeConfig { fee_config: Option<FeeConfigMsg> },
+    /// Pay out all collected fees to the fee collector. Callable by anyone
+    DistributeFees {},
+    /// Send balances the contract owns, like dust and donations, over IBC. Channel escrow and
+    /// collected fees can't be spent this way, and no protocol fee is charged. Only callable by
+    /// the owner
+    TreasuryTransfer {
+        amount: Amount,
+        channel: String,
+        remote_address: String,
+    },
+    /// Charge transfers of the denom (native or "cw20:<address>") by size bracket instead of the
+    /// flat bps, or go back to the bps with an empty list. Only callable by the owner
+    SetFeeTiers { denom: String, tiers: Vec<FeeTier> },
+    /// Pay out the fee shares credited to the sender as referrer
+    ClaimReferralFees {},
+    /// Register display information for a denom. Only callable by the owner
+    SetTokenMetadata {
+        denom: String,
+        metadata: TokenMetadata,
+    },
+    /// Only callable by the owner
+    RemoveTokenMetadata { denom: String },
+    /// Copy symbol, decimals and logo of a whitelisted cw20 from its TokenInfo and
+    /// MarketingInfo queries. Callable by anyone
+    SyncTokenMetadata { contract: String },
+    /// Ask a verifier contract about cw20s that are not whitelisted, or stop asking with None.
+    /// Only callable by the owner
+    SetWhitelistVerifier {
+        verifier: Option<WhitelistVerifierMsg>,
+    },
+    /// Mark a cw20 as taxing transfers, so it's only accepted through TransferCw20.
+    /// Only callable by the owner
+    SetFeeOnTransfer { contract: String, enabled: bool },
+    /// Restrict the channel to the tokens on its own allowlist, on top of the global whitelist.
+    /// Only callable by the owner
+    SetChannelAllowlist { channel: String, enabled: bool },
+    /// Add or remove denoms (native or "cw20:<address>") from the channel's allowlist.
+    /// Only callable by the owner
+    UpdateChannelAllowlist {
+        channel: String,
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Only let native denoms on the native allowlist be sent. Only callable by the owner
+    SetNativeAllowlist { enabled: bool },
+    /// Add or remove native denoms from the native allowlist. Only callable by the owner
+    UpdateNativeAllowlist {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Settle a transfer handed to the chain's transfer module once its packet was acknowledged
+    /// or timed out. Only callable by the owner
+    ResolveNativeTransfer {
+        id: u64,
+        resolution: NativeTransferResolution,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeConfigMsg {
+    /// share of each transfer kept as protocol fee, in basis points
+    pub bps: u64,
+    pub collector: String,
+    /// flat fee senders may pay instead of the bps fee
+    pub fee_denom: Option<FlatFee>,
+    /// share of the fee credited to the transfer's referrer, in basis points of the fee
+    #[serde(default)]
+    pub referrer_bps: u64,
+    /// split the fees among these by weight instead of paying collector. Rounding dust goes to
+    /// the first one
+    #[serde(default)]
+    pub collectors: Vec<FeeCollectorMsg>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeCollectorMsg {
+    pub address: String,
+    pub weight: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadLetterResolution {
+    /// Send the stranded funds to another local address
+    Redirect { recipient: String },
+    /// Drop the record and leave the funds with the contract
+    WriteOff {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NativeTransferResolution {
+    /// The packet was delivered, the funds are no longer held for the sender
+    Delivered {},
+    /// The transfer module refunded the contract, pass the funds on to the original sender
+    Refunded {},
+}
+
+/// Hooks we accept via Receive, besides a plain TransferMsg
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// Fund a recurring transfer with the received tokens
+    ScheduleTransfer(ScheduleMsg),
+    /// Stream the received tokens over a duration
+    CreateStream(StreamMsg),
+    /// Escrow the received tokens until the oracle reports the condition met
+    CreateConditional(ConditionalMsg),
+    /// Lock the received tokens in a hash-time-locked escrow
+    LockHtlc(LockHtlcMsg),
+    /// Hold the received tokens until released
+    Deposit { operator: Option<String> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StreamMsg {
+    /// The local channel to send the packets on
+    pub channel: String,
+    /// The remote address to send to
+    pub remote_address: String,
+    /// Seconds over which the funds are released linearly
+    pub duration: u64,
+    /// When the stream starts. If not specified, it starts now
+    pub start_time: Option<Timestamp>,
+    /// How long each packet lives in seconds. If not specified, use default_timeout
+    pub timeout: Option<u64>,
+    /// Instead of timeout, how long the packets live as a duration like "2h"
+    pub timeout_duration: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConditionalMsg {
+    /// The local channel to send the packet on
+    pub channel: String,
+    /// The remote address to send to
+    pub remote_address: String,
+    /// Name of a registered oracle
+    pub oracle: String,
+    /// Opaque to us, the oracle interprets it
+    pub condition: Binary,
+    /// After this the funds can only be reclaimed
+    pub expires: Expiration,
+    /// How long the packet lives in seconds once sent. If not specified, use default_timeout
+    pub timeout: Option<u64>,
+    /// Instead of timeout, how long the packets live as a duration like "2h"
+    pub timeout_duration: Option<String>,
+}
+
+/// The query a whitelist verifier answers with ApprovalResponse
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifierQueryMsg {
+    IsApproved { contract: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ApprovalResponse {
+    pub approved: bool,
 }
 
-/// This is the message we accept via Receive
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WhitelistVerifierMsg {
+    pub address: String,
+    /// seconds an approval is trusted without asking again
+    pub cache_ttl: Option<u64>,
+}
+
+/// The query every oracle adapter answers with ConditionResponse
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleQueryMsg {
+    CheckCondition { condition: Binary },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConditionResponse {
+    pub met: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockHtlcMsg {
+    /// Hex encoded sha256 hash of the secret preimage
+    pub hash: String,
+    /// Local address the funds are released to
+    pub recipient: String,
+    /// After this the funds can only be refunded
+    pub expires: Expiration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ScheduleMsg {
+    /// The local channel to send the packets on
+    pub channel: String,
+    /// The remote address to send to
+    pub remote_address: String,
+    /// How much to send on every run. The funds sent along are the schedule's whole budget
+    pub amount: Uint128,
+    /// Seconds between runs
+    pub interval: u64,
+    /// When the first run is due. If not specified, it is due right away
+    pub start_time: Option<Timestamp>,
+    /// When to stop. If not specified, runs continue until the funds are used up.
+    /// Funds left once the schedule ends are refunded
+    pub end: Option<ScheduleEnd>,
+    /// How long each packet lives in seconds. If not specified, use default_timeout
+    pub timeout: Option<u64>,
+    /// Instead of timeout, how long the packets live as a duration like "2h"
+    pub timeout_duration: Option<String>,
+}
+
+/// This is the message we accept via Receive
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
 pub struct TransferMsg {
     /// The local channel to send the packets on
     pub channel: String,
@@ -31,6 +371,24 @@ pub struct TransferMsg {
     pub remote_address: String,
     /// How long the packet lives in seconds. If not specified, use default_timeout
     pub timeout: Option<u64>,
+    /// Instead of timeout, how long the packet lives as a duration like "90s", "15m", "2h"
+    /// or "1h30m"
+    pub timeout_duration: Option<String>,
+    /// The denom (or an alias registered for the channel, like "usdc") the sent funds must match
+    pub denom: Option<String>,
+    /// Pay the flat protocol fee in the configured fee denom instead of from the bridged amount.
+    /// A native fee is attached as a second coin, a cw20 fee is pulled from the sender's allowance
+    #[serde(default)]
+    pub fee_in_fee_denom: bool,
+    /// Carried in the packet for the receiving chain, eg. an htlc preimage. Not supported on
+    /// channels in NativeTransfer mode
+    pub memo: Option<String>,
+    /// Wallet or front-end other than the sender that gets the configured share of the
+    /// protocol fee
+    pub referrer: Option<String>,
+    /// Name of a registered route. The packet goes to the route's first forwarder with a
+    /// forward memo delivering it to remote_address. Can't be combined with memo
+    pub route: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -45,12 +403,142 @@ pub enum QueryMsg {
     Channel { id: String },
     /// Show the Config. Returns ConfigResponse
     Config {},
-    /// Query if a given cw20 contract is allowed. Returns AllowedResponse
+    /// Lists recorded config and parameter changes, oldest first. Only the most recent
+    /// versions are kept. Return type: ConfigHistoryResponse.
+    ConfigHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Query if a given cw20 contract is allowed, by the whitelist or the verifier. Returns
+    /// WhitelistResponse
     Whitelisted { contract: String },
+    /// Returns the contract asked about cw20s missing from the whitelist.
+    /// Return type: WhitelistVerifierResponse.
+    WhitelistVerifier {},
+    /// Query up to 30 cw20 contracts at once. Return type: WhitelistedBatchResponse.
+    WhitelistedBatch { contracts: Vec<String> },
+    /// Returns how transfers over the channel are dispatched. Return type: ChannelModeResponse.
+    ChannelMode { id: String },
+    /// Returns how acknowledgements over the channel are read. Return type: AckFormatResponse.
+    AckFormat { channel: String },
+    /// Checks a remote address for the channel before it is used in a transfer.
+    /// Return type: VerifyRemoteAddressResponse.
+    VerifyRemoteAddress { channel: String, address: String },
+    /// Lists the stored parameters in key order. Return type: ParamsResponse.
+    Params {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns whether outgoing transfers are paused. Return type: PauseResponse.
+    Pause {},
+    /// Lists refunds that could not be delivered. Return type: DeadLettersResponse.
+    DeadLetters {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Resolves an alias to the denom to send on the channel. Return type: ResolveDenomResponse.
+    ResolveDenom { channel: String, alias: String },
+    /// Lists the aliases registered for the channel. Return type: DenomAliasesResponse.
+    DenomAliases {
+        channel: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the protocol fee settings. Return type: FeeConfigResponse.
+    FeeConfig {},
+    /// Returns the fee schedule of a denom. Return type: FeeTiersResponse.
+    FeeTiers { denom: String },
+    /// Returns the fee shares a referrer can claim. Return type: ReferralFeesResponse.
+    ReferralFees { referrer: String },
+    /// Lists the owner's treasury transfers, oldest first. Return type: TreasuryLogResponse.
+    TreasuryLog {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Returns the fees waiting to be distributed. Return type: CollectedFeesResponse.
+    CollectedFees {},
+    /// Returns the tokens permitted on the channel. Return type: ChannelAllowlistResponse.
+    ChannelAllowlist {
+        channel: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the native denoms that may be sent. Return type: NativeAllowlistResponse.
+    NativeAllowlist {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns a scheduled transfer. Return type: ScheduledTransfer.
+    Schedule { id: u64 },
+    /// Lists the active scheduled transfers funded by the sender. Return type: SchedulesResponse.
+    SchedulesBySender {
+        sender: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Returns a stream. Return type: Stream.
+    Stream { id: u64 },
+    /// Returns a conditional transfer. Return type: ConditionalTransfer.
+    Conditional { id: u64 },
+    /// Returns a deposit waiting to be released. Return type: Deposit.
+    Deposit { id: u64 },
+    /// Lists the deposits of a sender by id. Return type: DepositsResponse.
+    DepositsBySender {
+        sender: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Lists the registered oracles. Return type: OraclesResponse.
+    Oracles {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns a registered route. Return type: Route.
+    Route { name: String },
+    /// Lists the registered routes. Return type: RoutesResponse.
+    Routes {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Lists the packets sent to a remote address, oldest first. Return type: TransfersResponse.
+    TransfersByReceiver {
+        receiver: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Returns contract-lifetime totals. Return type: MetricsResponse.
+    Metrics {},
+    /// Returns a hash-time-locked escrow. Return type: Htlc.
+    Htlc { id: u64 },
+    /// Lists the hash-time-locked escrows locked against a hash. Return type: HtlcsResponse.
+    HtlcsByHash {
+        hash: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Returns the display information of a denom. Return type: TokenMetadataResponse.
+    TokenMetadata { denom: String },
+    /// Lists display information of all registered denoms. Return type: ListTokenMetadataResponse.
+    ListTokenMetadata {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Lists the unresolved transfers handed to the transfer module. Return type:
+    /// NativeTransfersResponse.
+    NativeTransfers {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct ConfigResponse {
     pub default_timeout: u64,
+    pub owner: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ConfigHistoryResponse {
+    pub versions: Vec<ConfigVersion>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -71,10 +559,237 @@ pub struct ChannelResponse {
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct WhitelistResponse {
+    /// whitelisted, or approved by the whitelist verifier
+    pub is_whitelist: bool,
+    /// must be sent with TransferCw20
+    pub fee_on_transfer: bool,
+    /// None for tokens that were never sent
+    pub stats: Option<TokenStats>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct WhitelistVerifierResponse {
+    pub verifier: Option<WhitelistVerifier>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct WhitelistedBatchResponse {
+    /// in the order of the queried contracts
+    pub results: Vec<WhitelistedBatchItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct WhitelistedBatchItem {
+    pub contract: String,
     pub is_whitelist: bool,
+    pub fee_on_transfer: bool,
+    pub stats: Option<TokenStats>,
+    /// why the contract could not be checked, eg. an invalid address
+    pub error: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct ListChannelsResponse {
     pub channels: Vec<ChannelInfo>,
 }
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ChannelModeResponse {
+    pub mode: ChannelMode,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct AckFormatResponse {
+    pub format: AckFormat,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ParamInfo {
+    pub key: String,
+    pub value: ParamValue,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ParamsResponse {
+    pub params: Vec<ParamInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PauseResponse {
+    pub paused: bool,
+    /// who triggered the active pause
+    pub source: Option<PauseSource>,
+    /// when the active pause lifts by itself
+    pub expires: Option<Expiration>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct DeadLettersResponse {
+    pub dead_letters: Vec<DeadLetter>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct NativeTransfersResponse {
+    pub transfers: Vec<NativeTransfer>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ResolveDenomResponse {
+    /// the registered denom, or the alias itself if nothing is registered under it
+    pub denom: String,
+    pub is_alias: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct DenomAlias {
+    pub alias: String,
+    pub denom: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct DenomAliasesResponse {
+    pub aliases: Vec<DenomAlias>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct VerifyRemoteAddressResponse {
+    pub valid: bool,
+    /// the prefix checked against, if the owner set one for the channel
+    pub expected_prefix: Option<String>,
+    pub issues: Vec<AddressIssue>,
+}
+
+/// Why a remote address was rejected
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressIssue {
+    UnknownChannel {},
+    /// bech32 strings are 8 to 90 characters
+    InvalidLength {
+        length: u64,
+    },
+    MixedCase {},
+    /// no "1" between a prefix and at least the checksum
+    NoSeparator {},
+    InvalidCharacter {
+        character: String,
+    },
+    InvalidChecksum {},
+    WrongPrefix {
+        expected: String,
+        found: String,
+    },
+    /// decodes to neither a 20 byte account nor a 32 byte contract address
+    InvalidPayloadLength {
+        length: u64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct FeeConfigResponse {
+    pub fee_config: Option<FeeConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct FeeTiersResponse {
+    pub denom: String,
+    pub tiers: Vec<FeeTier>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ReferralFeesResponse {
+    pub referrer: String,
+    pub fees: Vec<Amount>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TreasuryLogResponse {
+    pub entries: Vec<TreasuryEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct CollectedFeesResponse {
+    pub fees: Vec<Amount>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TokenMetadataResponse {
+    pub denom: String,
+    /// None if nothing is registered for the denom
+    pub metadata: Option<TokenMetadata>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ListTokenMetadataResponse {
+    pub tokens: Vec<TokenMetadataResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct OracleInfo {
+    pub name: String,
+    pub address: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct OraclesResponse {
+    pub oracles: Vec<OracleInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RouteInfo {
+    pub name: String,
+    pub route: Route,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RoutesResponse {
+    pub routes: Vec<RouteInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MetricsResponse {
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub packets_acked: u64,
+    pub packets_timed_out: u64,
+    pub packets_refunded: u64,
+    /// every fee accrued, including those already distributed
+    pub fees_collected: Vec<Amount>,
+    /// channels connected to the portal, which are never closed
+    pub active_channels: u64,
+    pub instantiate_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TransfersResponse {
+    pub transfers: Vec<TransferRecord>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct SchedulesResponse {
+    pub schedules: Vec<ScheduledTransfer>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct DepositsResponse {
+    pub deposits: Vec<Deposit>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct HtlcsResponse {
+    pub htlcs: Vec<Htlc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct NativeAllowlistResponse {
+    /// if false, any native denom may be sent
+    pub enabled: bool,
+    pub denoms: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ChannelAllowlistResponse {
+    /// if false, every token passing the global whitelist is permitted on the channel
+    pub enabled: bool,
+    pub tokens: Vec<String>,
+}
@@ -0,0 +1,91 @@
+use crate::amount::Amount;
+use crate::contract::execute_transfer;
+use crate::error::ContractError;
+use crate::ibc::CW20_PULL_ID;
+use crate::msg::TransferMsg;
+use crate::state::{PendingPull, FEE_CONFIG, PENDING_PULL};
+use cosmwasm_std::{
+    to_binary, Addr, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, SubMsg,
+    Uint128, WasmMsg,
+};
+use cw0::nonpayable;
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
+
+/// Pulls `amount` of the cw20 from the sender's allowance and bridges what actually arrived.
+/// Tokens that tax transfers credit less than `amount`, so the balance is measured before the
+/// pull and again in the reply.
+pub fn execute_transfer_cw20(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: String,
+    amount: Uint128,
+    msg: TransferMsg,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    if amount.is_zero() {
+        return Err(ContractError::NoFunds {});
+    }
+    // only a cw20 fee can be paid, it is pulled from the allowance like the tokens
+    if msg.fee_in_fee_denom {
+        let flat = FEE_CONFIG
+            .may_load(deps.storage)?
+            .and_then(|cfg| cfg.fee_denom);
+        if let Some(flat) = flat.filter(|f| !f.denom.starts_with("cw20:")) {
+            return Err(ContractError::FeeNotAttachable { denom: flat.denom });
+        }
+    }
+    let token = deps.api.addr_validate(&contract)?;
+    let balance_before = cw20_balance(deps.as_ref(), &token, &env.contract.address)?;
+    let pull = Cw20ExecuteMsg::TransferFrom {
+        owner: info.sender.to_string(),
+        recipient: env.contract.address.to_string(),
+        amount,
+    };
+    let pull = WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_binary(&pull)?,
+        funds: vec![],
+    };
+    // a singleton is enough, the reply runs right after the pull within this transaction
+    PENDING_PULL.save(
+        deps.storage,
+        &PendingPull {
+            token,
+            sender: info.sender,
+            balance_before,
+            msg,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(pull, CW20_PULL_ID))
+        .add_attribute("action", "pull_cw20")
+        .add_attribute("requested", amount.to_string()))
+}
+
+/// Bridges the balance credited by the pull
+pub(crate) fn finish_pull(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let pending = PENDING_PULL.load(deps.storage)?;
+    PENDING_PULL.remove(deps.storage);
+    let balance = cw20_balance(deps.as_ref(), &pending.token, &env.contract.address)?;
+    let credited = balance
+        .checked_sub(pending.balance_before)
+        .map_err(StdError::from)?;
+    let amount = Amount::Cw20(Cw20Coin {
+        address: pending.token.to_string(),
+        amount: credited,
+    });
+    let res = execute_transfer(deps, env, pending.msg, amount, pending.sender, None)?;
+    Ok(res.add_attribute("credited", credited.to_string()))
+}
+
+pub(crate) fn cw20_balance(deps: Deps, token: &Addr, address: &Addr) -> StdResult<Uint128> {
+    let res: BalanceResponse = deps.querier.query_wasm_smart(
+        token,
+        &Cw20QueryMsg::Balance {
+            address: address.to_string(),
+        },
+    )?;
+    Ok(res.balance)
+}
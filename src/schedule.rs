@@ -0,0 +1,310 @@
+use crate::amount::Amount;
+use crate::contract::{checked_timeout, execute_transfer, DEFAULT_LIMIT, MAX_LIMIT};
+use crate::error::ContractError;
+use crate::ibc::send_amount;
+use crate::msg::{ScheduleMsg, SchedulesResponse, TransferMsg};
+use crate::state::{
+    active_pause, increase_escrowed, is_known_channel, native_denom_allowed, queue_schedule,
+    reduce_escrowed, remove_schedule, unqueue_schedule, ScheduleEnd, ScheduledTransfer, SCHEDULES,
+    SCHEDULES_BY_SENDER, SCHEDULE_COUNT, SCHEDULE_QUEUE,
+};
+use crate::verifier::check_cw20;
+use cosmwasm_std::{
+    attr, Addr, Deps, DepsMut, Env, Event, MessageInfo, Order, Response, StdResult, Storage,
+    Timestamp, Uint128,
+};
+use cw_storage_plus::{Bound, PrimaryKey, U64Key};
+use std::convert::TryInto;
+
+/// Checks funds escrowed for later transfers could be sent on the channel at all, catching
+/// unknown channels, unlisted tokens and timeouts over the cap up front rather than on every run.
+/// Returns the packet timeout in seconds
+pub(crate) fn validate_escrow(
+    deps: Deps,
+    now: Timestamp,
+    channel: &str,
+    funds: &Amount,
+    timeout: Option<u64>,
+    timeout_duration: Option<&str>,
+) -> Result<Option<u64>, ContractError> {
+    if !is_known_channel(deps.storage, channel)? {
+        return Err(ContractError::NoSuchChannel {
+            id: channel.to_string(),
+        });
+    }
+    validate_token(deps, now, funds)?;
+    checked_timeout(deps.storage, timeout, timeout_duration)
+}
+
+/// Checks the token could be sent at all: a cw20 is whitelisted or approved by the verifier,
+/// a native denom is on the allowlist
+pub(crate) fn validate_token(
+    deps: Deps,
+    now: Timestamp,
+    funds: &Amount,
+) -> Result<(), ContractError> {
+    match funds {
+        Amount::Cw20(coin) => {
+            let addr = deps.api.addr_validate(&coin.address)?;
+            check_cw20(deps, now, &addr)?;
+        }
+        Amount::Native(coin) => {
+            if !native_denom_allowed(deps.storage, &coin.denom)? {
+                return Err(ContractError::NativeNotAllowed {
+                    denom: coin.denom.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn execute_schedule_transfer(
+    deps: DepsMut,
+    env: Env,
+    msg: ScheduleMsg,
+    funds: Amount,
+    sender: Addr,
+) -> Result<Response, ContractError> {
+    if msg.amount.is_zero() || msg.interval == 0 || funds.amount() < msg.amount {
+        return Err(ContractError::InvalidSchedule {});
+    }
+    let timeout = validate_escrow(
+        deps.as_ref(),
+        env.block.time,
+        &msg.channel,
+        &funds,
+        msg.timeout,
+        msg.timeout_duration.as_deref(),
+    )?;
+
+    let id = SCHEDULE_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+    SCHEDULE_COUNT.save(deps.storage, &id)?;
+    let schedule = ScheduledTransfer {
+        id,
+        sender,
+        channel: msg.channel,
+        remote_address: msg.remote_address,
+        denom: funds.denom(),
+        amount: msg.amount,
+        interval: msg.interval,
+        next_run: msg.start_time.unwrap_or(env.block.time),
+        end: msg.end,
+        runs: 0,
+        remaining: funds.amount(),
+        timeout,
+    };
+    SCHEDULES.save(deps.storage, id.into(), &schedule)?;
+    queue_schedule(deps.storage, &schedule)?;
+    SCHEDULES_BY_SENDER.save(deps.storage, (&schedule.sender, id.into()), &true)?;
+    increase_escrowed(deps.storage, &funds)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "schedule_transfer"),
+        attr("schedule_id", id.to_string()),
+        attr("sender", &schedule.sender),
+        attr("denom", &schedule.denom),
+        attr("amount", schedule.amount.to_string()),
+        attr("budget", schedule.remaining.to_string()),
+    ]))
+}
+
+/// Sends one packet for every due schedule. A schedule whose transfer is rejected (eg. the token
+/// was delisted) skips that run rather than blocking the crank for everyone else.
+pub fn execute_run_scheduled(
+    mut deps: DepsMut,
+    env: Env,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    // skipping runs while paused would silently drop payments, so wait for the unpause instead
+    if active_pause(deps.storage, &env.block)?.is_some() {
+        return Err(ContractError::Paused {});
+    }
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let due = due_schedules(deps.as_ref(), env.block.time, limit)?;
+
+    let mut res = Response::new().add_attribute("action", "run_scheduled");
+    for id in due {
+        let mut schedule = SCHEDULES.load(deps.storage, id.into())?;
+        // re-queued below under the next run time
+        unqueue_schedule(deps.storage, &schedule);
+
+        let send = schedule.amount.min(schedule.remaining);
+        let transfer = TransferMsg {
+            channel: schedule.channel.clone(),
+            remote_address: schedule.remote_address.clone(),
+            timeout: schedule.timeout,
+            ..Default::default()
+        };
+        let amount = Amount::from_parts(schedule.denom.clone(), send);
+        let mut event = Event::new("scheduled_run").add_attribute("schedule_id", id.to_string());
+        match execute_transfer(
+            deps.branch(),
+            env.clone(),
+            transfer,
+            amount,
+            schedule.sender.clone(),
+            None,
+        ) {
+            Ok(sent) => {
+                let sent_amount = Amount::from_parts(schedule.denom.clone(), send);
+                reduce_escrowed(deps.storage, &sent_amount)?;
+                schedule.remaining -= send;
+                schedule.runs += 1;
+                res.messages.extend(sent.messages);
+                res.events.extend(sent.events);
+                event = event
+                    .add_attribute("success", "true")
+                    .add_attributes(sent.attributes);
+            }
+            Err(err) => {
+                event = event
+                    .add_attribute("success", "false")
+                    .add_attribute("error", err.to_string());
+            }
+        }
+
+        schedule.next_run = schedule.next_run.plus_seconds(schedule.interval);
+        if schedule.is_finished() {
+            let closed = close_schedule(deps.storage, &schedule)?;
+            res.messages.extend(closed.messages);
+            event = event
+                .add_attributes(closed.attributes)
+                .add_attribute("finished", "true");
+        } else {
+            SCHEDULES.save(deps.storage, id.into(), &schedule)?;
+            queue_schedule(deps.storage, &schedule)?;
+        }
+        res = res.add_event(event);
+    }
+    Ok(res)
+}
+
+fn load_own_schedule(
+    deps: Deps,
+    id: u64,
+    sender: &Addr,
+) -> Result<ScheduledTransfer, ContractError> {
+    let schedule = SCHEDULES
+        .may_load(deps.storage, id.into())?
+        .ok_or(ContractError::NoSuchSchedule { id })?;
+    if schedule.sender != *sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(schedule)
+}
+
+// removes the schedule, refunding whatever escrow it has left
+fn close_schedule(storage: &mut dyn Storage, schedule: &ScheduledTransfer) -> StdResult<Response> {
+    remove_schedule(storage, schedule);
+    let mut res = Response::new().add_attribute("refund", schedule.remaining.to_string());
+    if !schedule.remaining.is_zero() {
+        let refund = Amount::from_parts(schedule.denom.clone(), schedule.remaining);
+        reduce_escrowed(storage, &refund)?;
+        res = res.add_message(send_amount(refund, schedule.sender.to_string()));
+    }
+    Ok(res)
+}
+
+pub fn execute_cancel_scheduled(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let schedule = load_own_schedule(deps.as_ref(), id, &info.sender)?;
+    let res = close_schedule(deps.storage, &schedule)?;
+    Ok(res.add_attributes(vec![
+        attr("action", "cancel_scheduled"),
+        attr("schedule_id", id.to_string()),
+    ]))
+}
+
+/// The next run keeps its time, a new interval applies from the run after it.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_update_scheduled(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+    remote_address: Option<String>,
+    amount: Option<Uint128>,
+    interval: Option<u64>,
+    end: Option<ScheduleEnd>,
+    timeout: Option<u64>,
+    timeout_duration: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut schedule = load_own_schedule(deps.as_ref(), id, &info.sender)?;
+    if amount == Some(Uint128::zero()) || interval == Some(0) {
+        return Err(ContractError::InvalidSchedule {});
+    }
+    if let Some(remote_address) = remote_address {
+        schedule.remote_address = remote_address;
+    }
+    if let Some(amount) = amount {
+        schedule.amount = amount;
+    }
+    if let Some(interval) = interval {
+        schedule.interval = interval;
+    }
+    if end.is_some() {
+        schedule.end = end;
+    }
+    if timeout.is_some() || timeout_duration.is_some() {
+        schedule.timeout = checked_timeout(deps.storage, timeout, timeout_duration.as_deref())?;
+    }
+
+    // a new end may already have been reached
+    let res = if schedule.is_finished() {
+        close_schedule(deps.storage, &schedule)?.add_attribute("finished", "true")
+    } else {
+        SCHEDULES.save(deps.storage, id.into(), &schedule)?;
+        Response::new()
+    };
+    Ok(res.add_attributes(vec![
+        attr("action", "update_scheduled"),
+        attr("schedule_id", id.to_string()),
+    ]))
+}
+
+// ids of schedules with next_run at or before now, earliest first
+fn due_schedules(deps: Deps, now: Timestamp, limit: usize) -> StdResult<Vec<u64>> {
+    let end = (U64Key::new(now.seconds() + 1), U64Key::new(0)).joined_key();
+    SCHEDULE_QUEUE
+        .keys(
+            deps.storage,
+            None,
+            Some(Bound::exclusive(end)),
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|k| {
+            // the schedule id is the last 8 bytes of the composite key
+            let id: [u8; 8] = k[k.len() - 8..].try_into().unwrap();
+            Ok(u64::from_be_bytes(id))
+        })
+        .collect()
+}
+
+pub fn query_schedule(deps: Deps, id: u64) -> StdResult<ScheduledTransfer> {
+    SCHEDULES.load(deps.storage, id.into())
+}
+
+pub fn query_schedules_by_sender(
+    deps: Deps,
+    sender: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SchedulesResponse> {
+    let sender = deps.api.addr_validate(&sender)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| Bound::exclusive(U64Key::new(id)));
+    let schedules = SCHEDULES_BY_SENDER
+        .prefix(&sender)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|k| {
+            let id: [u8; 8] = k.as_slice().try_into().unwrap();
+            SCHEDULES.load(deps.storage, u64::from_be_bytes(id).into())
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(SchedulesResponse { schedules })
+}
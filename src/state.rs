@@ -1,10 +1,20 @@
+use crate::amount::Amount;
 use crate::error::ContractError;
-use cosmwasm_std::{Addr, IbcEndpoint, StdResult, Storage, Uint128};
-use cw_storage_plus::{Item, Map};
+use crate::ibc::Ics20Packet;
+use crate::msg::TransferMsg;
+use cosmwasm_std::{
+    Addr, Binary, BlockInfo, Coin, IbcEndpoint, Order, StdError, StdResult, Storage, Timestamp,
+    Uint128,
+};
+use cw0::Expiration;
+use cw_storage_plus::{Item, Map, U64Key};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 pub const CONFIG: Item<Config> = Item::new("ics20_config");
+/// CONFIG as stored before it had an owner, read once by migrate
+pub const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("ics20_config");
 /// indexed by (channel_id, denom) maintaining the balance of the channel in that currency
 pub const CHANNEL_STATE: Map<(&str, &str), ChannelState> = Map::new("channel_state");
 
@@ -12,13 +22,372 @@ pub const CHANNEL_STATE: Map<(&str, &str), ChannelState> = Map::new("channel_sta
 pub const CHANNEL_INFO: Map<&str, ChannelInfo> = Map::new("channel_info");
 
 pub const WHITE_LIST: Map<&Addr, bool> = Map::new("whitelist");
+/// packets sent over portal channels, indexed by transfer id
+pub const TRANSFERS: Map<U64Key, TransferRecord> = Map::new("transfers");
+pub const TRANSFER_COUNT: Item<u64> = Item::new("transfer_count");
+/// indexed by (remote receiver, transfer id)
+pub const TRANSFERS_BY_RECEIVER: Map<(&str, U64Key), bool> = Map::new("transfers_by_receiver");
+/// indexed by (channel, sha256 of the packet data), the ids of unsettled transfers in send order.
+/// Acks only hand back the packet, identical packets are settled first in, first out
+pub const IN_FLIGHT: Map<(&str, &[u8]), Vec<u64>> = Map::new("in_flight");
+
+/// contract-lifetime counters
+pub const METRICS: Item<Metrics> = Item::new("metrics");
+/// every fee ever accrued, indexed by denom. Unlike COLLECTED_FEES this is never drained
+pub const LIFETIME_FEES: Map<&str, Uint128> = Map::new("lifetime_fees");
+
+/// usage of whitelisted cw20 tokens, indexed by token contract
+pub const TOKEN_STATS: Map<&Addr, TokenStats> = Map::new("token_stats");
+
+/// owner-managed settings for a channel, indexed by local channel id
+pub const CHANNEL_CONFIG: Map<&str, ChannelConfig> = Map::new("channel_config");
+
+/// the contract asked about cw20s missing from WHITE_LIST
+pub const WHITELIST_VERIFIER: Item<WhitelistVerifier> = Item::new("whitelist_verifier");
+/// verifier approvals kept for the verifier's cache_ttl
+pub const VERIFIER_APPROVALS: Map<&Addr, VerifierApproval> = Map::new("verifier_approvals");
+
+/// cw20s that tax transfers. They can only be bridged with TransferCw20, which measures
+/// what the contract was credited
+pub const FEE_ON_TRANSFER: Map<&Addr, bool> = Map::new("fee_on_transfer");
+/// the TransferCw20 waiting for its pull to finish
+pub const PENDING_PULL: Item<PendingPull> = Item::new("pending_pull");
+
+/// indexed by (channel_id, denom), the tokens permitted on channels that enable an allowlist.
+/// This is checked in addition to the global cw20 WHITE_LIST
+pub const CHANNEL_ALLOWLIST: Map<(&str, &str), bool> = Map::new("channel_allowlist");
+
+/// native denoms that may be sent while NATIVE_ALLOWLIST_ENABLED is set
+pub const NATIVE_ALLOWLIST: Map<&str, bool> = Map::new("native_allowlist");
+pub const NATIVE_ALLOWLIST_ENABLED: Item<bool> = Item::new("native_allowlist_enabled");
+
+/// Any native denom may be sent unless the allowlist is enabled
+pub fn native_denom_allowed(storage: &dyn Storage, denom: &str) -> StdResult<bool> {
+    let enabled = NATIVE_ALLOWLIST_ENABLED
+        .may_load(storage)?
+        .unwrap_or_default();
+    Ok(!enabled || NATIVE_ALLOWLIST.has(storage, denom))
+}
+
+/// indexed by (channel_id, alias), the full denom (native or "cw20:<address>") the alias stands for
+/// in TransferMsg.denom and ResolveDenom. Nothing on the wire is renamed
+pub const DENOM_ALIASES: Map<(&str, &str), String> = Map::new("denom_aliases");
+
+/// protocol fee settings, no fees are charged while unset
+pub const FEE_CONFIG: Item<FeeConfig> = Item::new("fee_config");
+/// protocol fees held by the contract until DistributeFees, indexed by denom
+pub const COLLECTED_FEES: Map<&str, Uint128> = Map::new("collected_fees");
+/// fee shares credited to referrers until they claim them, indexed by referrer and denom
+pub const REFERRER_FEES: Map<(&Addr, &str), Uint128> = Map::new("referrer_fees");
+/// what the contract holds for users outside the channel escrow, indexed by denom: schedules,
+/// streams, conditionals, htlcs, deposits, dead letters, referral fees and unresolved native
+/// transfers
+pub const ESCROWED: Map<&str, Uint128> = Map::new("escrowed");
+/// multi-hop routes transfers can name instead of building a forward memo, indexed by name
+pub const ROUTES: Map<&str, Route> = Map::new("routes");
+/// size brackets that replace the bps fee for a denom (native or "cw20:<address>")
+pub const FEE_TIERS: Map<&str, Vec<FeeTier>> = Map::new("fee_tiers");
+
+/// display information for the denoms the portal handles, indexed by denom (native or "cw20:<address>")
+pub const TOKEN_METADATA: Map<&str, TokenMetadata> = Map::new("token_metadata");
+
+/// recurring transfers funded up front, indexed by schedule id
+pub const SCHEDULES: Map<U64Key, ScheduledTransfer> = Map::new("schedules");
+pub const SCHEDULE_COUNT: Item<u64> = Item::new("schedule_count");
+/// indexed by (next_run in seconds, schedule id), for the crank to find due schedules in order
+pub const SCHEDULE_QUEUE: Map<(U64Key, U64Key), bool> = Map::new("schedule_queue");
+/// indexed by (sender, schedule id), for listing an address's active schedules
+pub const SCHEDULES_BY_SENDER: Map<(&Addr, U64Key), bool> = Map::new("schedules_by_sender");
+
+/// escrowed funds released linearly over time, indexed by stream id
+pub const STREAMS: Map<U64Key, Stream> = Map::new("streams");
+pub const STREAM_COUNT: Item<u64> = Item::new("stream_count");
+
+/// condition adapters registered by the owner, indexed by name
+pub const ORACLES: Map<&str, Addr> = Map::new("oracles");
+/// escrowed transfers waiting for an oracle condition, indexed by id
+pub const CONDITIONALS: Map<U64Key, ConditionalTransfer> = Map::new("conditionals");
+pub const CONDITIONAL_COUNT: Item<u64> = Item::new("conditional_count");
+
+/// funds waiting for their depositor to release them as a transfer, indexed by id
+pub const DEPOSITS: Map<U64Key, Deposit> = Map::new("deposits");
+pub const DEPOSIT_COUNT: Item<u64> = Item::new("deposit_count");
+pub const DEPOSITS_BY_SENDER: Map<(&Addr, U64Key), bool> = Map::new("deposits_by_sender");
+
+/// hash-time-locked escrows, indexed by id
+pub const HTLCS: Map<U64Key, Htlc> = Map::new("htlcs");
+pub const HTLC_COUNT: Item<u64> = Item::new("htlc_count");
+/// htlc ids by the hex encoded sha256 hash of the preimage
+pub const HTLCS_BY_HASH: Map<(&str, U64Key), bool> = Map::new("htlcs_by_hash");
+
+/// the most recent config and parameter changes, indexed by version
+pub const CONFIG_HISTORY: Map<U64Key, ConfigVersion> = Map::new("config_history");
+pub const CONFIG_VERSION: Item<u64> = Item::new("config_version");
+/// older versions are pruned
+pub const CONFIG_HISTORY_LIMIT: u64 = 100;
+
+/// every TreasuryTransfer the owner made, never pruned
+pub const TREASURY_LOG: Map<U64Key, TreasuryEntry> = Map::new("treasury_log");
+pub const TREASURY_LOG_COUNT: Item<u64> = Item::new("treasury_log_count");
+
+/// owner-managed tunables, indexed by parameter name
+pub const PARAMS: Map<&str, ParamValue> = Map::new("params");
+
+/// address allowed to pause transfers besides the owner
+pub const PARAM_GUARDIAN: &str = "guardian";
+/// if set, guardian pauses lift automatically after this many seconds
+pub const PARAM_GUARDIAN_PAUSE_SECONDS: &str = "guardian_pause_seconds";
+/// if set, guardian pauses lift automatically after this many blocks
+pub const PARAM_GUARDIAN_PAUSE_BLOCKS: &str = "guardian_pause_blocks";
+/// 1 day, how long a guardian pause lasts if neither duration is set. Only the owner pauses
+/// for good
+pub const DEFAULT_GUARDIAN_PAUSE_SECONDS: u64 = 24 * 60 * 60;
+/// longest timeout a transfer may ask for, in seconds. DEFAULT_MAX_TIMEOUT if unset
+pub const PARAM_MAX_TIMEOUT: &str = "max_timeout";
+/// 30 days, well below a timeout given in milliseconds by mistake
+pub const DEFAULT_MAX_TIMEOUT: u64 = 30 * 24 * 60 * 60;
+/// longest memo a transfer may carry, in bytes. DEFAULT_MAX_MEMO_LENGTH if unset
+pub const PARAM_MAX_MEMO_LENGTH: &str = "max_memo_length";
+/// the limit ibc-go's transfer module applies to memos
+pub const DEFAULT_MAX_MEMO_LENGTH: u64 = 32_768;
+/// if set, the largest packet data a transfer may send, in bytes
+pub const PARAM_MAX_PACKET_SIZE: &str = "max_packet_size";
+/// if set, the smallest amount an htlc may lock, in the token's base units
+pub const PARAM_MIN_HTLC_AMOUNT: &str = "min_htlc_amount";
+
+/// set while outgoing transfers are halted
+pub const PAUSE: Item<PauseInfo> = Item::new("pause");
 
 // Used to pass info from the ibc_packet_receive to the reply handler
 pub const REPLY_ARGS: Item<ReplyArgs> = Item::new("reply_args");
 
+// Used to pass info from a refund in ibc_packet_ack/timeout to the reply handler
+pub const REFUND_ARGS: Item<RefundArgs> = Item::new("refund_args");
+
+/// refunds that could not be delivered, waiting for the owner to resolve them
+pub const DEAD_LETTERS: Map<U64Key, DeadLetter> = Map::new("dead_letters");
+pub const DEAD_LETTER_COUNT: Item<u64> = Item::new("dead_letter_count");
+
+/// transfers handed to the chain's transfer module, indexed by id. The module refunds failed
+/// ones to the contract, so each stays in ESCROWED until the owner resolves it
+pub const NATIVE_TRANSFERS: Map<U64Key, NativeTransfer> = Map::new("native_transfers");
+pub const NATIVE_TRANSFER_COUNT: Item<u64> = Item::new("native_transfer_count");
+// ids of the native transfers sent in this transaction that wait for their sequence, in send order
+pub const NATIVE_AWAITING_SEQUENCE: Item<Vec<u64>> = Item::new("native_awaiting_sequence");
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct Config {
     pub default_timeout: u64,
+    /// the only address allowed to change contract settings
+    pub owner: Addr,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct LegacyConfig {
+    pub default_timeout: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigChange {
+    /// the whole config after the change
+    Config(Config),
+    /// None when the parameter was unset
+    Param {
+        key: String,
+        value: Option<ParamValue>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ConfigVersion {
+    pub version: u64,
+    pub change: ConfigChange,
+    pub changed_by: Addr,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TreasuryEntry {
+    pub id: u64,
+    pub amount: Amount,
+    pub channel: String,
+    pub remote_address: String,
+    pub sent_by: Addr,
+    pub height: u64,
+}
+
+/// Appends an entry to the treasury log and returns its id
+pub fn record_treasury_transfer(
+    storage: &mut dyn Storage,
+    amount: Amount,
+    channel: String,
+    remote_address: String,
+    sent_by: Addr,
+    height: u64,
+) -> StdResult<u64> {
+    let id = TREASURY_LOG_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    TREASURY_LOG_COUNT.save(storage, &id)?;
+    let entry = TreasuryEntry {
+        id,
+        amount,
+        channel,
+        remote_address,
+        sent_by,
+        height,
+    };
+    TREASURY_LOG.save(storage, id.into(), &entry)?;
+    Ok(id)
+}
+
+/// Appends a version to the config history, dropping the oldest beyond CONFIG_HISTORY_LIMIT
+pub fn record_config_change(
+    storage: &mut dyn Storage,
+    change: ConfigChange,
+    changed_by: Addr,
+    height: u64,
+) -> StdResult<u64> {
+    let version = CONFIG_VERSION.may_load(storage)?.unwrap_or_default() + 1;
+    CONFIG_VERSION.save(storage, &version)?;
+    let entry = ConfigVersion {
+        version,
+        change,
+        changed_by,
+        height,
+    };
+    CONFIG_HISTORY.save(storage, version.into(), &entry)?;
+    if version > CONFIG_HISTORY_LIMIT {
+        CONFIG_HISTORY.remove(storage, (version - CONFIG_HISTORY_LIMIT).into());
+    }
+    Ok(version)
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug, Default)]
+pub struct Metrics {
+    /// over portal channels and through the transfer module
+    pub packets_sent: u64,
+    /// delivered to the receiver, a bounced delivery acks an error and is not counted
+    pub packets_received: u64,
+    /// success acks
+    pub packets_acked: u64,
+    pub packets_timed_out: u64,
+    /// escrow returned after a timeout or an error ack. Refunds that end up as dead letters
+    /// are not counted
+    pub packets_refunded: u64,
+    pub channels_opened: u64,
+    pub instantiate_height: u64,
+}
+
+pub fn update_metrics<F: FnOnce(&mut Metrics)>(storage: &mut dyn Storage, f: F) -> StdResult<()> {
+    let mut metrics = METRICS.may_load(storage)?.unwrap_or_default();
+    f(&mut metrics);
+    METRICS.save(storage, &metrics)
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TransferRecord {
+    pub id: u64,
+    pub channel: String,
+    pub sender: String,
+    pub receiver: String,
+    pub denom: String,
+    pub amount: Uint128,
+    pub height: u64,
+    pub status: TransferStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    /// waiting for an ack or timeout
+    Pending,
+    /// the receiving chain accepted the packet
+    Delivered,
+    /// the escrow was returned to the sender
+    Refunded,
+}
+
+/// Records a packet about to be sent over the channel, returns the transfer id
+pub fn save_transfer_record(
+    storage: &mut dyn Storage,
+    channel: &str,
+    packet: &Ics20Packet,
+    data: &Binary,
+    height: u64,
+) -> StdResult<u64> {
+    let id = TRANSFER_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    TRANSFER_COUNT.save(storage, &id)?;
+    let record = TransferRecord {
+        id,
+        channel: channel.to_string(),
+        sender: packet.sender.clone(),
+        receiver: packet.receiver.clone(),
+        denom: packet.denom.clone(),
+        amount: packet.amount,
+        height,
+        status: TransferStatus::Pending,
+    };
+    TRANSFERS.save(storage, id.into(), &record)?;
+    TRANSFERS_BY_RECEIVER.save(storage, (&record.receiver, id.into()), &true)?;
+
+    let hash = Sha256::digest(data.as_slice());
+    IN_FLIGHT.update(storage, (channel, &hash), |ids| -> StdResult<_> {
+        let mut ids = ids.unwrap_or_default();
+        ids.push(id);
+        Ok(ids)
+    })?;
+    Ok(id)
+}
+
+/// Sets the final status of the oldest unsettled transfer with this packet data. Returns None for
+/// packets sent before transfers were recorded
+pub fn settle_transfer_record(
+    storage: &mut dyn Storage,
+    channel: &str,
+    data: &Binary,
+    status: TransferStatus,
+) -> StdResult<Option<u64>> {
+    let hash = Sha256::digest(data.as_slice());
+    let key = (channel, &hash[..]);
+    let mut ids = IN_FLIGHT.may_load(storage, key)?.unwrap_or_default();
+    if ids.is_empty() {
+        return Ok(None);
+    }
+    let id = ids.remove(0);
+    if ids.is_empty() {
+        IN_FLIGHT.remove(storage, key);
+    } else {
+        IN_FLIGHT.save(storage, key, &ids)?;
+    }
+    TRANSFERS.update(storage, id.into(), |record| -> StdResult<_> {
+        let mut record = record.ok_or_else(|| StdError::not_found("TransferRecord"))?;
+        record.status = status;
+        Ok(record)
+    })?;
+    Ok(Some(id))
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug, Default)]
+pub struct TokenStats {
+    pub transfer_count: u64,
+    pub last_used: Option<Timestamp>,
+    /// total sent over all channels
+    pub volume: Uint128,
+}
+
+pub fn record_token_use(
+    storage: &mut dyn Storage,
+    token: &Addr,
+    amount: Uint128,
+    time: Timestamp,
+) -> StdResult<TokenStats> {
+    TOKEN_STATS.update(storage, token, |stats| -> StdResult<_> {
+        let mut stats = stats.unwrap_or_default();
+        stats.transfer_count += 1;
+        stats.last_used = Some(time);
+        stats.volume = stats.volume.checked_add(amount)?;
+        Ok(stats)
+    })
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug, Default)]
@@ -34,6 +403,67 @@ pub struct ReplyArgs {
     pub amount: Uint128,
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RefundArgs {
+    pub channel: String,
+    pub amount: Amount,
+    /// the original sender, who the refund was addressed to
+    pub sender: String,
+    pub receiver: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct DeadLetter {
+    pub id: u64,
+    pub channel: String,
+    pub amount: Amount,
+    /// the original sender, who the refund was addressed to
+    pub sender: String,
+    /// the remote receiver of the failed packet
+    pub receiver: String,
+    /// why the refund could not be delivered
+    pub reason: String,
+    /// block height the refund failed at
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct NativeTransfer {
+    pub id: u64,
+    pub channel: String,
+    /// who the funds go back to if the transfer module refunds them
+    pub sender: Addr,
+    /// the remote receiver
+    pub receiver: String,
+    pub amount: Coin,
+    /// the packet sequence from the transfer module's send_packet event
+    pub sequence: Option<u64>,
+    /// block height the transfer was sent at
+    pub height: u64,
+}
+
+pub fn save_dead_letter(
+    storage: &mut dyn Storage,
+    args: RefundArgs,
+    reason: String,
+    height: u64,
+) -> StdResult<DeadLetter> {
+    let id = DEAD_LETTER_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    DEAD_LETTER_COUNT.save(storage, &id)?;
+    let letter = DeadLetter {
+        id,
+        channel: args.channel,
+        amount: args.amount,
+        sender: args.sender,
+        receiver: args.receiver,
+        reason,
+        height,
+    };
+    DEAD_LETTERS.save(storage, id.into(), &letter)?;
+    increase_escrowed(storage, &letter.amount)?;
+    Ok(letter)
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct ChannelInfo {
     /// id of this channel
@@ -44,6 +474,406 @@ pub struct ChannelInfo {
     pub connection_id: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug, Default)]
+pub struct ChannelConfig {
+    /// how transfers over this channel are dispatched
+    pub mode: ChannelMode,
+    /// only tokens in CHANNEL_ALLOWLIST may be sent over this channel
+    #[serde(default)]
+    pub token_allowlist: bool,
+    /// bech32 prefix of account addresses on the counterparty chain
+    #[serde(default)]
+    pub remote_prefix: Option<String>,
+    /// how the counterparty encodes acknowledgements
+    #[serde(default)]
+    pub ack_format: AckFormat,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelMode {
+    /// Send custom ics20 packets from this contract's port, with escrow tracked in CHANNEL_STATE
+    #[default]
+    Portal,
+    /// Send native coins with IbcMsg::Transfer through the chain's transfer module,
+    /// which holds the escrow. The channel id refers to a channel of the transfer port.
+    NativeTransfer,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AckFormat {
+    /// `{"result": ..}` or `{"error": ..}` as JSON, the ics20 convention
+    #[default]
+    Ics20Json,
+    /// ibc.core.channel.v1.Acknowledgement as protobuf, result in field 21 and error in field 22
+    Protobuf,
+    /// The acknowledgement is exactly `success` on success, and starts with `error_prefix` on
+    /// an error, reported as text. Anything else is rejected like a malformed ack.
+    Exact {
+        success: Binary,
+        error_prefix: Binary,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct FeeConfig {
+    /// share of each transfer kept as protocol fee, in basis points
+    pub bps: u64,
+    /// receives the collected fees on DistributeFees
+    pub collector: Addr,
+    /// flat fee senders may pay instead, so the bridged amount stays whole
+    pub fee_denom: Option<FlatFee>,
+    /// share of the fee credited to the transfer's referrer, in basis points of the fee
+    #[serde(default)]
+    pub referrer_bps: u64,
+    /// if set, DistributeFees splits the fees among these by weight instead of paying collector
+    #[serde(default)]
+    pub collectors: Vec<FeeCollector>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct FeeCollector {
+    pub address: Addr,
+    pub weight: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PendingPull {
+    pub token: Addr,
+    pub sender: Addr,
+    pub balance_before: Uint128,
+    pub msg: TransferMsg,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct WhitelistVerifier {
+    pub address: Addr,
+    /// seconds an approval is trusted without asking again, not cached if None
+    pub cache_ttl: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct VerifierApproval {
+    /// approvals of a replaced verifier no longer count
+    pub verifier: Addr,
+    pub expires: Timestamp,
+}
+
+/// A path through packet-forward-middleware chains. Transfers go out on `channel`, then every
+/// hop forwards them over its channel on the next chain
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Route {
+    pub channel: String,
+    pub hops: Vec<RouteHop>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RouteHop {
+    /// who receives the packet on the forwarding chain
+    pub receiver: HopReceiver,
+    /// channel on the forwarding chain to send the packet on
+    pub channel: String,
+    /// defaults to "transfer"
+    pub port: Option<String>,
+    /// forward timeout as a duration string, eg. "10m"
+    pub timeout: Option<String>,
+    pub retries: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HopReceiver {
+    /// "pfm", forward middleware since v7 holds the funds under an address it derives itself
+    Placeholder {},
+    /// a fixed address on the forwarding chain, for older forward middleware
+    Address { address: String },
+}
+
+/// A size bracket of the fee schedule. A transfer pays the fee of the last tier whose
+/// min_amount it reaches, transfers below the first tier pay the FeeConfig bps.
+/// Tiers are ignored when the fee is paid in the fee denom, that is always the flat
+/// FeeConfig fee.
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct FeeTier {
+    pub min_amount: Uint128,
+    pub fee: TierFee,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TierFee {
+    /// share of the transfer, in basis points
+    Bps(u64),
+    /// fixed amount of the transferred denom
+    Flat(Uint128),
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct FlatFee {
+    /// native denom, or "cw20:<address>" to pull the fee from the sender's allowance
+    pub denom: String,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TokenMetadata {
+    pub symbol: String,
+    pub decimals: u8,
+    /// link to the logo, or "embedded" when the cw20 contract stores it on chain
+    pub logo: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ScheduledTransfer {
+    pub id: u64,
+    /// who funded the schedule, used as packet sender and refund address
+    pub sender: Addr,
+    pub channel: String,
+    pub remote_address: String,
+    /// native denom or "cw20:<address>"
+    pub denom: String,
+    /// sent on every run, the last run may send less
+    pub amount: Uint128,
+    /// seconds between runs
+    pub interval: u64,
+    pub next_run: Timestamp,
+    pub end: Option<ScheduleEnd>,
+    /// runs completed so far
+    pub runs: u64,
+    /// escrow left to send
+    pub remaining: Uint128,
+    /// packet timeout in seconds, default_timeout if not set
+    pub timeout: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleEnd {
+    /// stop after this many runs
+    AfterRuns(u64),
+    /// no runs at or after this time
+    AtTime(Timestamp),
+}
+
+impl ScheduledTransfer {
+    /// true once no further run may happen, regardless of escrow left
+    pub fn is_finished(&self) -> bool {
+        if self.remaining.is_zero() {
+            return true;
+        }
+        match self.end {
+            Some(ScheduleEnd::AfterRuns(runs)) => self.runs >= runs,
+            Some(ScheduleEnd::AtTime(time)) => self.next_run >= time,
+            None => false,
+        }
+    }
+}
+
+pub fn queue_schedule(storage: &mut dyn Storage, schedule: &ScheduledTransfer) -> StdResult<()> {
+    SCHEDULE_QUEUE.save(
+        storage,
+        (schedule.next_run.seconds().into(), schedule.id.into()),
+        &true,
+    )
+}
+
+pub fn unqueue_schedule(storage: &mut dyn Storage, schedule: &ScheduledTransfer) {
+    SCHEDULE_QUEUE.remove(
+        storage,
+        (schedule.next_run.seconds().into(), schedule.id.into()),
+    );
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Stream {
+    pub id: u64,
+    /// who funded the stream, used as packet sender and refund address
+    pub sender: Addr,
+    pub channel: String,
+    pub remote_address: String,
+    /// native denom or "cw20:<address>"
+    pub denom: String,
+    pub total: Uint128,
+    /// sent in packets so far
+    pub streamed: Uint128,
+    /// both bounds move forward by the time spent paused
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub paused_at: Option<Timestamp>,
+    /// packet timeout in seconds, default_timeout if not set
+    pub timeout: Option<u64>,
+}
+
+impl Stream {
+    /// The part of the total released by the given time, frozen while paused
+    pub fn vested(&self, now: Timestamp) -> Uint128 {
+        let now = self.paused_at.unwrap_or(now);
+        if now <= self.start {
+            Uint128::zero()
+        } else if now >= self.end {
+            self.total
+        } else {
+            let elapsed = now.nanos() - self.start.nanos();
+            let duration = self.end.nanos() - self.start.nanos();
+            self.total.multiply_ratio(elapsed, duration)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ConditionalTransfer {
+    pub id: u64,
+    /// who funded the transfer, used as packet sender and refund address
+    pub sender: Addr,
+    pub channel: String,
+    pub remote_address: String,
+    pub amount: Amount,
+    /// name of the registered oracle that decides
+    pub oracle: String,
+    /// passed to the oracle as is, eg. a price threshold or attestation id
+    pub condition: Binary,
+    /// after this the transfer can only be reclaimed
+    pub expires: Expiration,
+    /// packet timeout in seconds, default_timeout if not set
+    pub timeout: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Deposit {
+    pub id: u64,
+    /// who deposited, the only one who can cancel and the packet sender once released
+    pub sender: Addr,
+    /// may release the deposit besides the sender, eg. a contract that approves or batches
+    pub operator: Option<Addr>,
+    pub amount: Amount,
+    pub created: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Htlc {
+    pub id: u64,
+    pub hash: String,
+    /// who locked the funds, refunded after expiry
+    pub sender: Addr,
+    /// who the funds are released to once the preimage is revealed
+    pub recipient: Addr,
+    pub amount: Amount,
+    pub expires: Expiration,
+}
+
+/// Drops a schedule from storage, the crank queue and the sender index
+pub fn remove_schedule(storage: &mut dyn Storage, schedule: &ScheduledTransfer) {
+    unqueue_schedule(storage, schedule);
+    SCHEDULES_BY_SENDER.remove(storage, (&schedule.sender, schedule.id.into()));
+    SCHEDULES.remove(storage, schedule.id.into());
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseSource {
+    Owner,
+    Guardian,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PauseInfo {
+    pub source: PauseSource,
+    /// Expiration::Never for pauses that must be lifted explicitly
+    pub expires: Expiration,
+}
+
+/// Returns the active pause, if any. Pauses past their expiration no longer count.
+pub fn active_pause(storage: &dyn Storage, block: &BlockInfo) -> StdResult<Option<PauseInfo>> {
+    let pause = PAUSE
+        .may_load(storage)?
+        .filter(|pause| !pause.expires.is_expired(block));
+    Ok(pause)
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamValue {
+    U64(u64),
+    Bool(bool),
+    String(String),
+    Addr(Addr),
+}
+
+impl ParamValue {
+    fn kind(&self) -> &'static str {
+        match self {
+            ParamValue::U64(_) => "u64",
+            ParamValue::Bool(_) => "bool",
+            ParamValue::String(_) => "string",
+            ParamValue::Addr(_) => "addr",
+        }
+    }
+}
+
+fn param_type_error(key: &str, expected: &str, found: &ParamValue) -> StdError {
+    StdError::generic_err(format!(
+        "Parameter {} is a {}, expected {}",
+        key,
+        found.kind(),
+        expected
+    ))
+}
+
+// The param_* helpers return None if the parameter was never set (or was unset),
+// so callers can fall back to their built-in default.
+pub fn param_u64(storage: &dyn Storage, key: &str) -> StdResult<Option<u64>> {
+    match PARAMS.may_load(storage, key)? {
+        None => Ok(None),
+        Some(ParamValue::U64(v)) => Ok(Some(v)),
+        Some(other) => Err(param_type_error(key, "u64", &other)),
+    }
+}
+
+pub fn param_bool(storage: &dyn Storage, key: &str) -> StdResult<Option<bool>> {
+    match PARAMS.may_load(storage, key)? {
+        None => Ok(None),
+        Some(ParamValue::Bool(v)) => Ok(Some(v)),
+        Some(other) => Err(param_type_error(key, "bool", &other)),
+    }
+}
+
+pub fn param_string(storage: &dyn Storage, key: &str) -> StdResult<Option<String>> {
+    match PARAMS.may_load(storage, key)? {
+        None => Ok(None),
+        Some(ParamValue::String(v)) => Ok(Some(v)),
+        Some(other) => Err(param_type_error(key, "string", &other)),
+    }
+}
+
+pub fn param_addr(storage: &dyn Storage, key: &str) -> StdResult<Option<Addr>> {
+    match PARAMS.may_load(storage, key)? {
+        None => Ok(None),
+        Some(ParamValue::Addr(v)) => Ok(Some(v)),
+        Some(other) => Err(param_type_error(key, "addr", &other)),
+    }
+}
+
+/// Channels opened with this contract, or transfer-module channels set to NativeTransfer mode
+pub fn is_known_channel(storage: &dyn Storage, channel: &str) -> StdResult<bool> {
+    if CHANNEL_INFO.has(storage, channel) {
+        return Ok(true);
+    }
+    let mode = CHANNEL_CONFIG
+        .may_load(storage, channel)?
+        .unwrap_or_default()
+        .mode;
+    Ok(mode == ChannelMode::NativeTransfer)
+}
+
+/// Returns the denom registered under the alias for this channel, or the input when it is no alias
+pub fn resolve_denom(storage: &dyn Storage, channel: &str, denom: &str) -> StdResult<String> {
+    let resolved = DENOM_ALIASES
+        .may_load(storage, (channel, denom))?
+        .unwrap_or_else(|| denom.to_string());
+    Ok(resolved)
+}
+
 // this is like increase, but it only "un-subtracts" (= adds) outstanding, not total_sent
 // calling `reduce_channel_balance` and then `undo_reduce_channel_balance` should leave state unchanged.
 pub fn undo_reduce_channel_balance(
@@ -60,6 +890,36 @@ pub fn undo_reduce_channel_balance(
     Ok(())
 }
 
+pub fn increase_escrowed(storage: &mut dyn Storage, amount: &Amount) -> StdResult<()> {
+    ESCROWED.update(storage, &amount.denom(), |orig| -> StdResult<_> {
+        Ok(orig.unwrap_or_default() + amount.amount())
+    })?;
+    Ok(())
+}
+
+// saturating, escrows made before the counter existed were never added to it
+pub fn reduce_escrowed(storage: &mut dyn Storage, amount: &Amount) -> StdResult<()> {
+    ESCROWED.update(storage, &amount.denom(), |orig| -> StdResult<_> {
+        Ok(orig.unwrap_or_default().saturating_sub(amount.amount()))
+    })?;
+    Ok(())
+}
+
+/// What the contract holds of the denom on behalf of senders, summed over all channels
+pub fn total_outstanding(storage: &dyn Storage, denom: &str) -> StdResult<Uint128> {
+    let channels: Vec<Vec<u8>> = CHANNEL_INFO
+        .keys(storage, None, None, Order::Ascending)
+        .collect();
+    let mut total = Uint128::zero();
+    for channel in channels {
+        let channel = String::from_utf8(channel)?;
+        if let Some(state) = CHANNEL_STATE.may_load(storage, (&channel, denom))? {
+            total += state.outstanding;
+        }
+    }
+    Ok(total)
+}
+
 pub fn increase_channel_balance(
     storage: &mut dyn Storage,
     channel: &str,
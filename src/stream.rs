@@ -0,0 +1,251 @@
+use crate::amount::Amount;
+use crate::contract::{execute_transfer, DEFAULT_LIMIT, MAX_LIMIT};
+use crate::error::ContractError;
+use crate::ibc::send_amount;
+use crate::msg::{StreamMsg, TransferMsg};
+use crate::schedule::validate_escrow;
+use crate::state::{
+    active_pause, increase_escrowed, reduce_escrowed, Stream, STREAMS, STREAM_COUNT,
+};
+use cosmwasm_std::{
+    attr, Addr, Deps, DepsMut, Env, Event, MessageInfo, Order, Response, StdResult, Timestamp,
+    Uint128,
+};
+use cw_storage_plus::{Bound, U64Key};
+
+pub fn execute_create_stream(
+    deps: DepsMut,
+    env: Env,
+    msg: StreamMsg,
+    funds: Amount,
+    sender: Addr,
+) -> Result<Response, ContractError> {
+    let start = msg.start_time.unwrap_or(env.block.time);
+    let end = msg
+        .duration
+        .checked_mul(1_000_000_000)
+        .and_then(|nanos| start.nanos().checked_add(nanos))
+        .map(Timestamp::from_nanos);
+    let end = match end {
+        Some(end) if !funds.is_empty() && msg.duration != 0 => end,
+        _ => return Err(ContractError::InvalidStream {}),
+    };
+    let timeout = validate_escrow(
+        deps.as_ref(),
+        env.block.time,
+        &msg.channel,
+        &funds,
+        msg.timeout,
+        msg.timeout_duration.as_deref(),
+    )?;
+
+    let id = STREAM_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+    STREAM_COUNT.save(deps.storage, &id)?;
+    let stream = Stream {
+        id,
+        sender,
+        channel: msg.channel,
+        remote_address: msg.remote_address,
+        denom: funds.denom(),
+        total: funds.amount(),
+        streamed: Default::default(),
+        start,
+        end,
+        paused_at: None,
+        timeout,
+    };
+    STREAMS.save(deps.storage, id.into(), &stream)?;
+    increase_escrowed(deps.storage, &funds)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "create_stream"),
+        attr("stream_id", id.to_string()),
+        attr("sender", &stream.sender),
+        attr("denom", &stream.denom),
+        attr("total", stream.total.to_string()),
+        attr("end", stream.end.to_string()),
+    ]))
+}
+
+/// Sends one packet per active stream with whatever it released since the last tick. A stream
+/// whose transfer is rejected keeps its funds for the next tick.
+pub fn execute_tick_streams(
+    mut deps: DepsMut,
+    env: Env,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    if active_pause(deps.storage, &env.block)?.is_some() {
+        return Err(ContractError::Paused {});
+    }
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| Bound::exclusive(U64Key::new(id)));
+    let streams = STREAMS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, stream)| stream))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut res = Response::new().add_attribute("action", "tick_streams");
+    for mut stream in streams {
+        let due = stream.vested(env.block.time) - stream.streamed;
+        if due.is_zero() {
+            continue;
+        }
+        let transfer = TransferMsg {
+            channel: stream.channel.clone(),
+            remote_address: stream.remote_address.clone(),
+            timeout: stream.timeout,
+            ..Default::default()
+        };
+        let amount = Amount::from_parts(stream.denom.clone(), due);
+        let mut event = Event::new("stream_tick").add_attribute("stream_id", stream.id.to_string());
+        match execute_transfer(
+            deps.branch(),
+            env.clone(),
+            transfer,
+            amount,
+            stream.sender.clone(),
+            None,
+        ) {
+            Ok(sent) => {
+                let sent_amount = Amount::from_parts(stream.denom.clone(), due);
+                reduce_escrowed(deps.storage, &sent_amount)?;
+                stream.streamed += due;
+                res.messages.extend(sent.messages);
+                res.events.extend(sent.events);
+                event = event
+                    .add_attribute("success", "true")
+                    .add_attributes(sent.attributes);
+                if stream.streamed == stream.total {
+                    STREAMS.remove(deps.storage, stream.id.into());
+                    event = event.add_attribute("finished", "true");
+                } else {
+                    STREAMS.save(deps.storage, stream.id.into(), &stream)?;
+                }
+            }
+            Err(err) => {
+                event = event
+                    .add_attribute("success", "false")
+                    .add_attribute("error", err.to_string());
+            }
+        }
+        res = res.add_event(event);
+    }
+    Ok(res)
+}
+
+fn load_own_stream(deps: Deps, id: u64, sender: &Addr) -> Result<Stream, ContractError> {
+    let stream = STREAMS
+        .may_load(deps.storage, id.into())?
+        .ok_or(ContractError::NoSuchStream { id })?;
+    if stream.sender != *sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(stream)
+}
+
+pub fn execute_pause_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = load_own_stream(deps.as_ref(), id, &info.sender)?;
+    if stream.paused_at.is_some() {
+        return Err(ContractError::StreamPaused { id });
+    }
+    stream.paused_at = Some(env.block.time);
+    STREAMS.save(deps.storage, id.into(), &stream)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "pause_stream"),
+        attr("stream_id", id.to_string()),
+    ]))
+}
+
+pub fn execute_resume_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = load_own_stream(deps.as_ref(), id, &info.sender)?;
+    let paused_at = stream
+        .paused_at
+        .take()
+        .ok_or(ContractError::StreamNotPaused { id })?;
+    // a stream paused before it started only moves as far as the time it was paused after start
+    let paused_from = paused_at.max(stream.start);
+    if env.block.time > paused_from {
+        let paused_for = env.block.time.nanos() - paused_from.nanos();
+        stream.start = stream.start.plus_nanos(paused_for);
+        stream.end = stream.end.plus_nanos(paused_for);
+    }
+    STREAMS.save(deps.storage, id.into(), &stream)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "resume_stream"),
+        attr("stream_id", id.to_string()),
+        attr("end", stream.end.to_string()),
+    ]))
+}
+
+/// Stops a stream. What vested but was not ticked yet still goes to the remote address,
+/// only the unvested part is refunded. If that transfer is rejected the vested part is
+/// refunded as well, the sender must always get the stream's funds back.
+pub fn execute_cancel_stream(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let stream = load_own_stream(deps.as_ref(), id, &info.sender)?;
+    STREAMS.remove(deps.storage, id.into());
+
+    let vested = stream.vested(env.block.time);
+    let mut owed = vested - stream.streamed;
+    let mut refund = stream.total - vested;
+    let left = Amount::from_parts(stream.denom.clone(), stream.total - stream.streamed);
+    reduce_escrowed(deps.storage, &left)?;
+    let mut res = Response::new();
+    if !owed.is_zero() {
+        let transfer = TransferMsg {
+            channel: stream.channel.clone(),
+            remote_address: stream.remote_address.clone(),
+            timeout: stream.timeout,
+            ..Default::default()
+        };
+        let amount = Amount::from_parts(stream.denom.clone(), owed);
+        match execute_transfer(
+            deps.branch(),
+            env,
+            transfer,
+            amount,
+            stream.sender.clone(),
+            None,
+        ) {
+            Ok(sent) => res = res.add_submessages(sent.messages).add_events(sent.events),
+            Err(err) => {
+                res = res.add_attribute("transfer_error", err.to_string());
+                refund += owed;
+                owed = Uint128::zero();
+            }
+        }
+    }
+    res = res.add_attributes(vec![
+        attr("action", "cancel_stream"),
+        attr("stream_id", id.to_string()),
+        attr("streamed", owed.to_string()),
+        attr("refund", refund.to_string()),
+    ]);
+    if !refund.is_zero() {
+        let amount = Amount::from_parts(stream.denom, refund);
+        res = res.add_message(send_amount(amount, stream.sender.to_string()));
+    }
+    Ok(res)
+}
+
+pub fn query_stream(deps: Deps, id: u64) -> StdResult<Stream> {
+    STREAMS.load(deps.storage, id.into())
+}
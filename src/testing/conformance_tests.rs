@@ -0,0 +1,71 @@
+use crate::conformance::{ACK_ERROR, ACK_SUCCESS, PACKET, PACKET_LARGE_AMOUNT, PACKET_WITH_MEMO};
+use crate::error::ContractError;
+use crate::ibc::{ack_success, Ics20Ack, Ics20Packet};
+use cosmwasm_std::{from_slice, to_vec, Uint128};
+
+// decoding and encoding again must give back exactly the bytes ibc-go sent
+fn round_trip<T: serde::Serialize + serde::de::DeserializeOwned>(vector: &str) -> T {
+    let decoded: T = from_slice(vector.as_bytes()).unwrap();
+    assert_eq!(
+        String::from_utf8(to_vec(&decoded).unwrap()).unwrap(),
+        vector
+    );
+    decoded
+}
+
+#[test]
+fn packet_round_trips() {
+    let packet: Ics20Packet = round_trip(PACKET);
+    assert_eq!(packet.amount, Uint128::new(1000000));
+    assert_eq!(packet.denom, "transfer/channel-0/uatom");
+    assert_eq!(packet.receiver, "juno1receiver");
+    assert_eq!(packet.sender, "cosmos1sender");
+    assert_eq!(packet.memo, None);
+    assert_eq!(packet.v, None);
+}
+
+#[test]
+fn packet_with_memo_round_trips() {
+    let packet: Ics20Packet = round_trip(PACKET_WITH_MEMO);
+    assert_eq!(
+        packet.memo.as_deref(),
+        Some(r#"{"forward":{"port":"transfer","channel":"channel-7"}}"#)
+    );
+}
+
+#[test]
+fn large_amount_round_trips_but_is_rejected() {
+    let packet: Ics20Packet = round_trip(PACKET_LARGE_AMOUNT);
+    assert_eq!(packet.amount, Uint128::MAX);
+    assert_eq!(
+        packet.validate().unwrap_err(),
+        ContractError::AmountOverflow {}
+    );
+}
+
+#[test]
+fn our_packets_match_ibc_go() {
+    let packet = Ics20Packet::new(
+        Uint128::new(1000000),
+        "transfer/channel-0/uatom",
+        "cosmos1sender",
+        "juno1receiver",
+    );
+    assert_eq!(String::from_utf8(to_vec(&packet).unwrap()).unwrap(), PACKET);
+}
+
+#[test]
+fn acks_round_trip() {
+    let ack: Ics20Ack = round_trip(ACK_SUCCESS);
+    assert_eq!(ack, Ics20Ack::Result(vec![1].into()));
+    let ack: Ics20Ack = round_trip(ACK_ERROR);
+    assert_eq!(
+        ack,
+        Ics20Ack::Error("ABCI code: 1: error handling packet: see events for details".to_string())
+    );
+}
+
+#[test]
+fn our_success_ack_matches_ibc_go() {
+    assert_eq!(ack_success().as_slice(), ACK_SUCCESS.as_bytes());
+}
@@ -0,0 +1,751 @@
+use crate::amount::Amount;
+use crate::contract::{execute, query};
+use crate::ibc::{
+    ack_success, ibc_packet_ack, ibc_packet_receive, ibc_packet_timeout, reply, Ics20Ack,
+    Ics20Packet,
+};
+use crate::msg::{
+    AckFormatResponse, ChannelResponse, DeadLetterResolution, DeadLettersResponse, ExecuteMsg,
+    LockHtlcMsg, MetricsResponse, QueryMsg, TransferMsg, TransfersResponse,
+};
+use crate::state::{AckFormat, TransferStatus};
+use crate::testing::test_helpers::{
+    mock_received_packet, mock_sent_packet, setup, DEFAULT_TIMEOUT,
+};
+use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    attr, coins, from_binary, to_binary, BankMsg, ContractResult, CosmosMsg, Deps, Event,
+    IbcAcknowledgement, IbcMsg, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, Reply,
+    Uint128, WasmMsg,
+};
+use cw0::Expiration;
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use sha2::{Digest, Sha256};
+
+fn query_dead_letters(deps: Deps) -> DeadLettersResponse {
+    let raw = query(
+        deps,
+        mock_env(),
+        QueryMsg::DeadLetters {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    from_binary(&raw).unwrap()
+}
+
+#[test]
+fn failed_refund_becomes_dead_letter() {
+    let send_channel = "channel-9";
+    let cw20_addr = "my-token";
+    let denom = format!("cw20:{}", cw20_addr);
+    let mut deps = setup(&[send_channel], &[cw20_addr]);
+
+    // prepare some escrow for the packet
+    let transfer = TransferMsg {
+        channel: send_channel.to_string(),
+        remote_address: "remote-rcpt".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "local-sender".into(),
+        amount: Uint128::new(987654321),
+        msg: to_binary(&transfer).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info(cw20_addr, &[]), msg).unwrap();
+
+    // the timeout refunds the escrow to the sender
+    let packet = mock_sent_packet(send_channel, 987654321, &denom, "local-sender");
+    let res =
+        ibc_packet_timeout(deps.as_mut(), mock_env(), IbcPacketTimeoutMsg::new(packet)).unwrap();
+    assert_eq!(1, res.messages.len());
+
+    // but the cw20 contract rejects the refund
+    let reply_msg = Reply {
+        id: res.messages[0].id,
+        result: ContractResult::Err("cw20 contract is gone".to_string()),
+    };
+    let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+    assert_eq!(res.attributes[0], attr("action", "dead_letter"));
+    // the refund event of the timeout is followed by one saying it went nowhere
+    assert_eq!(
+        event_attr(&res.events, "dead_letter", "dead_letter_id"),
+        Some("1".to_string())
+    );
+    assert_eq!(
+        event_attr(&res.events, "dead_letter", "refund_amount"),
+        Some("987654321".to_string())
+    );
+    // nothing was refunded after all
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Metrics {}).unwrap();
+    let metrics: MetricsResponse = from_binary(&raw).unwrap();
+    assert_eq!(metrics.packets_refunded, 0);
+
+    let letters = query_dead_letters(deps.as_ref()).dead_letters;
+    assert_eq!(1, letters.len());
+    assert_eq!(letters[0].id, 1);
+    assert_eq!(letters[0].sender, "local-sender");
+    assert_eq!(letters[0].reason, "cw20 contract is gone");
+    assert_eq!(
+        letters[0].amount,
+        Amount::from_parts(denom, Uint128::new(987654321))
+    );
+
+    // the owner redirects the funds elsewhere
+    let msg = ExecuteMsg::ResolveDeadLetter {
+        id: 1,
+        resolution: DeadLetterResolution::Redirect {
+            recipient: "rescue".to_string(),
+        },
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: cw20_addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "rescue".to_string(),
+                amount: Uint128::new(987654321),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert_eq!(0, query_dead_letters(deps.as_ref()).dead_letters.len());
+}
+
+#[test]
+fn refund_to_self_is_dead_lettered_immediately() {
+    let send_channel = "channel-9";
+    let mut deps = setup(&[send_channel], &[]);
+
+    let transfer = TransferMsg {
+        channel: send_channel.to_string(),
+        remote_address: "remote-rcpt".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let info = mock_info(MOCK_CONTRACT_ADDR, &coins(500, "ucosm"));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::Transfer(transfer),
+    )
+    .unwrap();
+
+    let packet = mock_sent_packet(send_channel, 500, "ucosm", MOCK_CONTRACT_ADDR);
+    let res =
+        ibc_packet_timeout(deps.as_mut(), mock_env(), IbcPacketTimeoutMsg::new(packet)).unwrap();
+    assert_eq!(0, res.messages.len());
+    assert_eq!(
+        event_attr(&res.events, "dead_letter", "dead_letter_id"),
+        Some("1".to_string())
+    );
+
+    let letters = query_dead_letters(deps.as_ref()).dead_letters;
+    assert_eq!(1, letters.len());
+    assert_eq!(letters[0].reason, "refund recipient is the contract itself");
+
+    let msg = ExecuteMsg::ResolveDeadLetter {
+        id: 1,
+        resolution: DeadLetterResolution::WriteOff {},
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    assert_eq!(0, res.messages.len());
+    assert_eq!(0, query_dead_letters(deps.as_ref()).dead_letters.len());
+}
+
+#[test]
+fn preimage_in_packet_memo_releases_htlc() {
+    let channel = "channel-9";
+    let mut deps = setup(&[channel], &[]);
+
+    let preimage = hex::encode(b"open sesame");
+    let hash = hex::encode(Sha256::digest(b"open sesame"));
+    let lock = LockHtlcMsg {
+        hash: hash.clone(),
+        recipient: "bob".to_string(),
+        expires: Expiration::AtHeight(mock_env().block.height + 100),
+    };
+    let info = mock_info("alice", &coins(100, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::LockHtlc(lock)).unwrap();
+
+    // escrow some funds on the channel so the incoming packet can be paid out
+    let transfer = TransferMsg {
+        channel: channel.to_string(),
+        remote_address: "remote-rcpt".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let info = mock_info("local-sender", &coins(500, "ucosm"));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::Transfer(transfer),
+    )
+    .unwrap();
+
+    let memo = format!(
+        r#"{{"forward":{{"port":"transfer"}},"htlc":{{"preimage":"{}"}}}}"#,
+        preimage
+    );
+    let data = Ics20Packet::new(
+        Uint128::new(200),
+        "transfer/channel-95/ucosm",
+        "remote-sender",
+        "local-rcpt",
+    )
+    .with_memo(Some(memo));
+    let packet = mock_received_packet(channel, &data);
+    let msg = IbcPacketReceiveMsg::new(packet);
+    let res = ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(2, res.messages.len());
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "bob".to_string(),
+            amount: coins(100, "ucosm"),
+        })
+    );
+    assert!(res
+        .attributes
+        .iter()
+        .any(|a| a.key == "htlc_claimed" && a.value == "1"));
+    query(deps.as_ref(), mock_env(), QueryMsg::Htlc { id: 1 }).unwrap_err();
+
+    // the same preimage again only fails the claim, not the transfer
+    let memo = format!(r#"{{"htlc":{{"preimage":"{}"}}}}"#, preimage);
+    let data = Ics20Packet::new(
+        Uint128::new(200),
+        "transfer/channel-95/ucosm",
+        "remote-sender",
+        "local-rcpt",
+    )
+    .with_memo(Some(memo));
+    let msg = IbcPacketReceiveMsg::new(mock_received_packet(channel, &data));
+    let res = ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(1, res.messages.len());
+    assert!(res.attributes.iter().any(|a| a.key == "htlc_error"));
+}
+
+#[test]
+fn transfers_are_tracked_by_receiver() {
+    let channel = "channel-9";
+    let mut deps = setup(&[channel], &[]);
+
+    for (amount, receiver) in [
+        (100, "remote-rcpt"),
+        (200, "someone-else"),
+        (300, "remote-rcpt"),
+    ] {
+        let transfer = TransferMsg {
+            channel: channel.to_string(),
+            remote_address: receiver.to_string(),
+            timeout: None,
+            denom: None,
+            fee_in_fee_denom: false,
+            memo: None,
+            referrer: None,
+            route: None,
+            timeout_duration: None,
+        };
+        let info = mock_info("local-sender", &coins(amount, "ucosm"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::Transfer(transfer),
+        )
+        .unwrap();
+    }
+
+    let packet = mock_sent_packet(channel, 100, "ucosm", "local-sender");
+    let ack = IbcAcknowledgement::new(to_binary(&Ics20Ack::Result(b"1".into())).unwrap());
+    ibc_packet_ack(deps.as_mut(), mock_env(), IbcPacketAckMsg::new(ack, packet)).unwrap();
+    let packet = mock_sent_packet(channel, 300, "ucosm", "local-sender");
+    ibc_packet_timeout(deps.as_mut(), mock_env(), IbcPacketTimeoutMsg::new(packet)).unwrap();
+
+    let msg = QueryMsg::TransfersByReceiver {
+        receiver: "remote-rcpt".to_string(),
+        start_after: None,
+        limit: None,
+    };
+    let res: TransfersResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(2, res.transfers.len());
+    assert_eq!(res.transfers[0].id, 1);
+    assert_eq!(res.transfers[0].amount, Uint128::new(100));
+    assert_eq!(res.transfers[0].status, TransferStatus::Delivered);
+    assert_eq!(res.transfers[1].id, 3);
+    assert_eq!(res.transfers[1].status, TransferStatus::Refunded);
+}
+
+#[test]
+fn metrics_count_packet_lifecycle() {
+    let mut deps = setup(&["channel-9", "channel-10"], &[]);
+
+    for amount in [100, 300] {
+        let transfer = TransferMsg {
+            channel: "channel-9".to_string(),
+            remote_address: "remote-rcpt".to_string(),
+            timeout: None,
+            denom: None,
+            fee_in_fee_denom: false,
+            memo: None,
+            referrer: None,
+            route: None,
+            timeout_duration: None,
+        };
+        let info = mock_info("local-sender", &coins(amount, "ucosm"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::Transfer(transfer),
+        )
+        .unwrap();
+    }
+    let packet = mock_sent_packet("channel-9", 100, "ucosm", "local-sender");
+    let ack = IbcAcknowledgement::new(to_binary(&Ics20Ack::Result(b"1".into())).unwrap());
+    ibc_packet_ack(deps.as_mut(), mock_env(), IbcPacketAckMsg::new(ack, packet)).unwrap();
+    let packet = mock_sent_packet("channel-9", 300, "ucosm", "local-sender");
+    ibc_packet_timeout(deps.as_mut(), mock_env(), IbcPacketTimeoutMsg::new(packet)).unwrap();
+
+    let data = Ics20Packet::new(
+        Uint128::new(50),
+        "transfer/channel-95/ucosm",
+        "remote-sender",
+        "local-rcpt",
+    );
+    let msg = IbcPacketReceiveMsg::new(mock_received_packet("channel-9", &data));
+    ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+    // a delivery that bounces is not counted as received
+    let msg = IbcPacketReceiveMsg::new(mock_received_packet("channel-9", &data));
+    let res = ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+    let reply_msg = Reply {
+        id: res.messages[0].id,
+        result: ContractResult::Err("receiver rejects funds".to_string()),
+    };
+    reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Metrics {}).unwrap();
+    let metrics: MetricsResponse = from_binary(&raw).unwrap();
+    assert_eq!(
+        metrics,
+        MetricsResponse {
+            packets_sent: 2,
+            packets_received: 1,
+            packets_acked: 1,
+            packets_timed_out: 1,
+            packets_refunded: 1,
+            fees_collected: vec![],
+            active_channels: 2,
+            instantiate_height: mock_env().block.height,
+        }
+    );
+}
+
+fn event_attr(events: &[Event], ty: &str, key: &str) -> Option<String> {
+    let event = events.iter().find(|e| e.ty == ty)?;
+    event
+        .attributes
+        .iter()
+        .find(|a| a.key == key)
+        .map(|a| a.value.clone())
+}
+
+#[test]
+fn events_use_transfer_module_keys() {
+    let channel = "channel-9";
+    let mut deps = setup(&[channel], &[]);
+
+    let transfer = TransferMsg {
+        channel: channel.to_string(),
+        remote_address: "remote-rcpt".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: Some("hello".to_string()),
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let info = mock_info("local-sender", &coins(100, "ucosm"));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::Transfer(transfer),
+    )
+    .unwrap();
+    let data = match &res.messages[0].msg {
+        CosmosMsg::Ibc(IbcMsg::SendPacket { data, .. }) => data.clone(),
+        _ => panic!("no packet sent"),
+    };
+    assert_eq!(
+        event_attr(&res.events, "ibc_transfer", "memo"),
+        Some("hello".to_string())
+    );
+    assert_eq!(
+        event_attr(&res.events, "ibc_transfer", "amount"),
+        Some("100".to_string())
+    );
+    assert_eq!(
+        event_attr(&res.events, "ibc_transfer", "packet_data"),
+        Some(String::from_utf8(data.to_vec()).unwrap())
+    );
+    let timeout = mock_env().block.time.plus_seconds(DEFAULT_TIMEOUT).nanos();
+    assert_eq!(
+        event_attr(&res.events, "ibc_transfer", "packet_timeout_timestamp"),
+        Some(timeout.to_string())
+    );
+
+    let packet = mock_sent_packet(channel, 100, "ucosm", "local-sender");
+    let res =
+        ibc_packet_timeout(deps.as_mut(), mock_env(), IbcPacketTimeoutMsg::new(packet)).unwrap();
+    assert_eq!(
+        event_attr(&res.events, "timeout", "refund_receiver"),
+        Some("local-sender".to_string())
+    );
+    assert_eq!(
+        event_attr(&res.events, "timeout", "refund_denom"),
+        Some("ucosm".to_string())
+    );
+    assert_eq!(
+        event_attr(&res.events, "timeout", "refund_amount"),
+        Some("100".to_string())
+    );
+    assert_eq!(
+        event_attr(&res.events, "timeout", "packet_sequence"),
+        Some("3".to_string())
+    );
+}
+
+#[test]
+fn refund_event_states_cause() {
+    let channel = "channel-9";
+    let mut deps = setup(&[channel], &[]);
+    for amount in [100, 300] {
+        let transfer = TransferMsg {
+            channel: channel.to_string(),
+            remote_address: "remote-rcpt".to_string(),
+            timeout: None,
+            denom: None,
+            fee_in_fee_denom: false,
+            memo: None,
+            referrer: None,
+            route: None,
+            timeout_duration: None,
+        };
+        let info = mock_info("local-sender", &coins(amount, "ucosm"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::Transfer(transfer),
+        )
+        .unwrap();
+    }
+
+    let packet = mock_sent_packet(channel, 100, "ucosm", "local-sender");
+    let error = "ABCI code: 5: error handling packet: see events for details";
+    let ack = IbcAcknowledgement::new(to_binary(&Ics20Ack::Error(error.to_string())).unwrap());
+    let res = ibc_packet_ack(deps.as_mut(), mock_env(), IbcPacketAckMsg::new(ack, packet)).unwrap();
+    assert_eq!(
+        event_attr(&res.events, "refund", "cause"),
+        Some("error_ack".to_string())
+    );
+    assert_eq!(
+        event_attr(&res.events, "refund", "error_code"),
+        Some("5".to_string())
+    );
+    assert_eq!(
+        event_attr(&res.events, "refund", "error_message"),
+        Some("error handling packet: see events for details".to_string())
+    );
+
+    let packet = mock_sent_packet(channel, 300, "ucosm", "local-sender");
+    let res =
+        ibc_packet_timeout(deps.as_mut(), mock_env(), IbcPacketTimeoutMsg::new(packet)).unwrap();
+    assert_eq!(
+        event_attr(&res.events, "refund", "cause"),
+        Some("timeout".to_string())
+    );
+    assert_eq!(
+        event_attr(&res.events, "refund", "refund_amount"),
+        Some("300".to_string())
+    );
+    assert_eq!(event_attr(&res.events, "refund", "error_code"), None);
+}
+
+#[test]
+fn protobuf_acks_are_parsed_per_channel() {
+    let channel = "channel-9";
+    let mut deps = setup(&[channel], &[]);
+    let msg = ExecuteMsg::SetAckFormat {
+        channel: channel.to_string(),
+        format: AckFormat::Protobuf,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    let raw = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::AckFormat {
+            channel: channel.to_string(),
+        },
+    )
+    .unwrap();
+    let res: AckFormatResponse = from_binary(&raw).unwrap();
+    assert_eq!(res.format, AckFormat::Protobuf);
+
+    for amount in [100, 300] {
+        let transfer = TransferMsg {
+            channel: channel.to_string(),
+            remote_address: "remote-rcpt".to_string(),
+            timeout: None,
+            denom: None,
+            fee_in_fee_denom: false,
+            memo: None,
+            referrer: None,
+            route: None,
+            timeout_duration: None,
+        };
+        let info = mock_info("local-sender", &coins(amount, "ucosm"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::Transfer(transfer),
+        )
+        .unwrap();
+    }
+
+    // field 21 holding the single byte 0x01
+    let packet = mock_sent_packet(channel, 100, "ucosm", "local-sender");
+    let ack = IbcAcknowledgement::new(vec![0xaa, 0x01, 0x01, 0x01]);
+    let res = ibc_packet_ack(deps.as_mut(), mock_env(), IbcPacketAckMsg::new(ack, packet)).unwrap();
+    assert!(res.messages.is_empty());
+
+    // field 22 holding the error string
+    let error = b"ABCI code: 5: insufficient funds";
+    let mut data = vec![0xb2, 0x01, error.len() as u8];
+    data.extend_from_slice(error);
+    let packet = mock_sent_packet(channel, 300, "ucosm", "local-sender");
+    let res = ibc_packet_ack(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketAckMsg::new(IbcAcknowledgement::new(data), packet),
+    )
+    .unwrap();
+    assert_eq!(
+        event_attr(&res.events, "refund", "cause"),
+        Some("error_ack".to_string())
+    );
+    assert_eq!(
+        event_attr(&res.events, "refund", "error_message"),
+        Some("insufficient funds".to_string())
+    );
+    assert_eq!(res.messages.len(), 1);
+
+    // a json ack is not a protobuf acknowledgement
+    let packet = mock_sent_packet(channel, 100, "ucosm", "local-sender");
+    let ack = IbcAcknowledgement::new(to_binary(&Ics20Ack::Result(b"1".into())).unwrap());
+    ibc_packet_ack(deps.as_mut(), mock_env(), IbcPacketAckMsg::new(ack, packet)).unwrap_err();
+}
+
+#[test]
+fn exact_acks_need_the_success_bytes_or_the_error_prefix() {
+    let channel = "channel-9";
+    let mut deps = setup(&[channel], &[]);
+    let format = AckFormat::Exact {
+        success: b"ok".into(),
+        error_prefix: b"err:".into(),
+    };
+    let msg = ExecuteMsg::SetAckFormat {
+        channel: channel.to_string(),
+        format,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    assert_eq!(res.attributes[2], attr("format", "exact"));
+
+    for amount in [100, 300] {
+        let transfer = TransferMsg {
+            channel: channel.to_string(),
+            remote_address: "remote-rcpt".to_string(),
+            timeout: None,
+            denom: None,
+            fee_in_fee_denom: false,
+            memo: None,
+            referrer: None,
+            route: None,
+            timeout_duration: None,
+        };
+        let info = mock_info("local-sender", &coins(amount, "ucosm"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::Transfer(transfer),
+        )
+        .unwrap();
+    }
+
+    let packet = mock_sent_packet(channel, 100, "ucosm", "local-sender");
+    let ack = IbcAcknowledgement::new(b"ok".to_vec());
+    let res = ibc_packet_ack(deps.as_mut(), mock_env(), IbcPacketAckMsg::new(ack, packet)).unwrap();
+    assert!(res.messages.is_empty());
+
+    let packet = mock_sent_packet(channel, 300, "ucosm", "local-sender");
+    let ack = IbcAcknowledgement::new(b"err: insufficient funds".to_vec());
+    let res = ibc_packet_ack(deps.as_mut(), mock_env(), IbcPacketAckMsg::new(ack, packet)).unwrap();
+    assert_eq!(
+        event_attr(&res.events, "refund", "cause"),
+        Some("error_ack".to_string())
+    );
+    assert_eq!(res.messages.len(), 1);
+
+    // unknown bytes are not taken for an error, which would refund a delivered transfer
+    let packet = mock_sent_packet(channel, 100, "ucosm", "local-sender");
+    let ack = IbcAcknowledgement::new(b"okay".to_vec());
+    ibc_packet_ack(deps.as_mut(), mock_env(), IbcPacketAckMsg::new(ack, packet)).unwrap_err();
+}
+
+#[test]
+fn packets_without_v_keep_the_channel_balance() {
+    let channel = "channel-9";
+    let mut deps = setup(&[channel], &[]);
+    for amount in [100, 300] {
+        let transfer = TransferMsg {
+            channel: channel.to_string(),
+            remote_address: "remote-rcpt".to_string(),
+            timeout: None,
+            denom: None,
+            fee_in_fee_denom: false,
+            memo: None,
+            referrer: None,
+            route: None,
+            timeout_duration: None,
+        };
+        let info = mock_info("local-sender", &coins(amount, "ucosm"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::Transfer(transfer),
+        )
+        .unwrap();
+    }
+    let balance = |deps: Deps| {
+        let msg = QueryMsg::Channel {
+            id: channel.to_string(),
+        };
+        let res: ChannelResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        res.balances
+    };
+    assert_eq!(
+        balance(deps.as_ref()),
+        vec![Amount::from_parts("ucosm".to_string(), Uint128::new(400))]
+    );
+
+    // the transfer record marks them as already counted, a delivery adds nothing
+    let packet = mock_sent_packet(channel, 100, "ucosm", "local-sender");
+    let ack = IbcAcknowledgement::new(ack_success());
+    ibc_packet_ack(deps.as_mut(), mock_env(), IbcPacketAckMsg::new(ack, packet)).unwrap();
+    assert_eq!(
+        balance(deps.as_ref()),
+        vec![Amount::from_parts("ucosm".to_string(), Uint128::new(400))]
+    );
+    let packet = mock_sent_packet(channel, 300, "ucosm", "local-sender");
+    ibc_packet_timeout(deps.as_mut(), mock_env(), IbcPacketTimeoutMsg::new(packet)).unwrap();
+    assert_eq!(
+        balance(deps.as_ref()),
+        vec![Amount::from_parts("ucosm".to_string(), Uint128::new(100))]
+    );
+}
+
+#[test]
+fn packets_sent_before_the_field_reorder_still_settle() {
+    let channel = "channel-9";
+    let mut deps = setup(&[channel], &[]);
+    for amount in [100, 300] {
+        let transfer = TransferMsg {
+            channel: channel.to_string(),
+            remote_address: "remote-rcpt".to_string(),
+            timeout: None,
+            denom: None,
+            fee_in_fee_denom: false,
+            memo: None,
+            referrer: None,
+            route: None,
+            timeout_duration: None,
+        };
+        let info = mock_info("local-sender", &coins(amount, "ucosm"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::Transfer(transfer),
+        )
+        .unwrap();
+    }
+
+    // older builds wrote v before memo, and v as null when unset
+    let old_packet = |data: &str| {
+        let mut packet = mock_sent_packet(channel, 0, "ucosm", "local-sender");
+        packet.data = data.as_bytes().into();
+        packet
+    };
+    let packet = old_packet(
+        r#"{"amount":"100","denom":"ucosm","receiver":"remote-rcpt","sender":"local-sender","v":2}"#,
+    );
+    let ack = IbcAcknowledgement::new(to_binary(&Ics20Ack::Result(vec![1].into())).unwrap());
+    let res = ibc_packet_ack(deps.as_mut(), mock_env(), IbcPacketAckMsg::new(ack, packet)).unwrap();
+    assert!(res.messages.is_empty());
+
+    let packet = old_packet(
+        r#"{"amount":"300","denom":"ucosm","receiver":"remote-rcpt","sender":"local-sender","v":2,"memo":"note"}"#,
+    );
+    let res =
+        ibc_packet_timeout(deps.as_mut(), mock_env(), IbcPacketTimeoutMsg::new(packet)).unwrap();
+    assert_eq!(
+        event_attr(&res.events, "refund", "refund_amount"),
+        Some("300".to_string())
+    );
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "local-sender".to_string(),
+            amount: coins(300, "ucosm")
+        })
+    );
+
+    // pre-v2 packets never added to the channel balance, so they do not take from it either
+    let packet = old_packet(
+        r#"{"amount":"50","denom":"ucosm","receiver":"remote-rcpt","sender":"local-sender","v":null}"#,
+    );
+    let res =
+        ibc_packet_timeout(deps.as_mut(), mock_env(), IbcPacketTimeoutMsg::new(packet)).unwrap();
+    assert_eq!(
+        event_attr(&res.events, "refund", "refund_amount"),
+        Some("50".to_string())
+    );
+}
@@ -1,2 +1,5 @@
+#[cfg(feature = "conformance")]
+mod conformance_tests;
+mod ibc_tests;
 mod tests;
 mod test_helpers;
\ No newline at end of file
@@ -1,7 +1,8 @@
-use cosmwasm_std::{DepsMut, IbcChannel, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcEndpoint, OwnedDeps};
-use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
+use std::collections::HashMap;
+use cosmwasm_std::{from_slice, to_binary, Binary, Coin, ContractResult, DepsMut, Empty, IbcChannel, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcEndpoint, IbcPacket, OwnedDeps, Querier, QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery};
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use crate::contract::instantiate;
-use crate::ibc::{ibc_channel_connect, ibc_channel_open, ICS20_ORDERING, ICS20_VERSION};
+use crate::ibc::{ibc_channel_connect, ibc_channel_open, Ics20Packet, ICS20_ORDERING, ICS20_VERSION};
 use crate::msg::InitMsg;
 use crate::state::ChannelInfo;
 
@@ -10,11 +11,69 @@ pub const CONTRACT_PORT: &str = "ibc:wasm1234567890abcdef";
 pub const REMOTE_PORT: &str = "transfer";
 pub const CONNECTION_ID: &str = "connection-2";
 
+type SmartHandler = Box<dyn Fn(&Binary) -> ContractResult<Binary>>;
+
+/// MockQuerier does not answer wasm queries, this one answers smart queries
+/// for contracts that registered a handler
+pub struct WasmMockQuerier {
+    base: MockQuerier,
+    smart: HashMap<String, SmartHandler>,
+}
+
+impl Querier for WasmMockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        match &request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                match self.smart.get(contract_addr) {
+                    Some(handler) => SystemResult::Ok(handler(msg)),
+                    None => SystemResult::Err(SystemError::NoSuchContract {
+                        addr: contract_addr.clone(),
+                    }),
+                }
+            }
+            _ => self.base.handle_query(&request),
+        }
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn update_smart<F>(&mut self, contract: &str, handler: F)
+    where
+        F: Fn(&Binary) -> ContractResult<Binary> + 'static,
+    {
+        self.smart.insert(contract.to_string(), Box::new(handler));
+    }
+
+    pub fn update_balance(&mut self, addr: &str, balance: Vec<Coin>) {
+        self.base.update_balance(addr, balance);
+    }
+}
+
+pub fn mock_dependencies() -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: WasmMockQuerier {
+            base: MockQuerier::new(&[(MOCK_CONTRACT_ADDR, &[])]),
+            smart: HashMap::new(),
+        },
+    }
+}
+
 pub fn setup(
     channels: &[&str],
     whitelist: &[&str],
-) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
-    let mut deps = mock_dependencies(&[]);
+) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let mut deps = mock_dependencies();
 
     let white_list = whitelist
         .iter()
@@ -71,4 +130,37 @@ pub fn add_channel(mut deps: DepsMut, channel_id: &str) {
     ibc_channel_open(deps.branch(), mock_env(), open_msg).unwrap();
     let connect_msg = IbcChannelConnectMsg::new_ack(channel, ICS20_VERSION);
     ibc_channel_connect(deps.branch(), mock_env(), connect_msg).unwrap();
-}
\ No newline at end of file
+}
+// a packet as this contract would have sent it on the given channel
+pub fn mock_sent_packet(my_channel: &str, amount: u128, denom: &str, sender: &str) -> IbcPacket {
+    let data = Ics20Packet::new(Uint128::new(amount), denom, sender, "remote-rcpt");
+    IbcPacket::new(
+        to_binary(&data).unwrap(),
+        IbcEndpoint {
+            port_id: CONTRACT_PORT.to_string(),
+            channel_id: my_channel.to_string(),
+        },
+        IbcEndpoint {
+            port_id: REMOTE_PORT.to_string(),
+            channel_id: format!("{}5", my_channel),
+        },
+        3,
+        mock_env().block.time.plus_seconds(DEFAULT_TIMEOUT).into(),
+    )
+}
+// a packet the remote chain sent to us on the given channel
+pub fn mock_received_packet(my_channel: &str, data: &Ics20Packet) -> IbcPacket {
+    IbcPacket::new(
+        to_binary(data).unwrap(),
+        IbcEndpoint {
+            port_id: REMOTE_PORT.to_string(),
+            channel_id: format!("{}5", my_channel),
+        },
+        IbcEndpoint {
+            port_id: CONTRACT_PORT.to_string(),
+            channel_id: my_channel.to_string(),
+        },
+        4,
+        mock_env().block.time.plus_seconds(DEFAULT_TIMEOUT).into(),
+    )
+}
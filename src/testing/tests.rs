@@ -1,12 +1,31 @@
-use cosmwasm_std::{coin, coins, CosmosMsg, from_binary, IbcMsg, StdError, to_binary, Uint128};
-use cosmwasm_std::testing::{mock_env, mock_info};
-use cw0::PaymentError;
-use cw20::Cw20ReceiveMsg;
-use crate::contract::{execute, query};
+use cosmwasm_std::{coin, coins, Addr, BankMsg, Binary, ContractResult, CosmosMsg, Deps, from_binary, IbcMsg, OwnedDeps, Response, StdError, to_binary, Uint128, WasmMsg, Reply, SubMsgExecutionResponse, Event};
+use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cw0::{Expiration, PaymentError};
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, LogoInfo, MarketingInfoResponse, TokenInfoResponse, BalanceResponse};
+use crate::amount::Amount;
+use crate::contract::{execute, query, migrate};
 use crate::error::ContractError;
-use crate::ibc::Ics20Packet;
-use crate::msg::{ChannelResponse, ExecuteMsg, ListChannelsResponse, QueryMsg, TransferMsg};
+use crate::ibc::{reply, Ics20Packet};
+use crate::msg::{
+    ChannelAllowlistResponse, ChannelModeResponse, ChannelResponse, CollectedFeesResponse, ExecuteMsg,
+    FeeConfigMsg, ListChannelsResponse, ListTokenMetadataResponse, ParamInfo, ParamsResponse, PauseResponse,
+    QueryMsg, ResolveDenomResponse, TokenMetadataResponse, TransferMsg, ReceiveMsg, ScheduleMsg,
+    SchedulesResponse, LockHtlcMsg, StreamMsg, ConditionalMsg, ConditionResponse, OracleQueryMsg,
+    ConfigHistoryResponse, WhitelistResponse, MetricsResponse, NativeAllowlistResponse, FeeTiersResponse,
+    ReferralFeesResponse, AddressIssue, VerifyRemoteAddressResponse, RouteInfo, RoutesResponse,
+    TreasuryLogResponse, WhitelistedBatchResponse, ApprovalResponse, VerifierQueryMsg, WhitelistVerifierMsg,
+    WhitelistVerifierResponse, FeeCollectorMsg, HtlcsResponse, DepositsResponse, MigrateMsg,
+    NativeTransferResolution, NativeTransfersResponse,
+};
+use crate::state::{
+    param_bool, param_u64, ChannelMode, FlatFee, ParamValue, TokenMetadata, CHANNEL_STATE, ScheduleEnd,
+    ScheduledTransfer, Htlc, Stream, Config, ConfigChange, ConfigVersion, TokenStats, FeeTier, TierFee, Route,
+    RouteHop, HopReceiver, TreasuryEntry, Deposit, LEGACY_CONFIG, LegacyConfig, CONFIG,
+};
 use crate::testing::test_helpers::{DEFAULT_TIMEOUT, mock_channel_info, setup};
+use sha2::{Digest, Sha256};
+use std::cell::Cell;
+use std::rc::Rc;
 
 #[test]
 fn setup_and_query() {
@@ -50,6 +69,12 @@ fn proper_checks_on_execute_native() {
         channel: send_channel.to_string(),
         remote_address: "foreign-address".to_string(),
         timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
     };
 
     // works with proper funds
@@ -111,6 +136,12 @@ fn proper_checks_on_execute_cw20() {
         channel: send_channel.to_string(),
         remote_address: "foreign-address".to_string(),
         timeout: Some(7777),
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
     };
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: "my-account".into(),
@@ -157,6 +188,12 @@ fn execute_cw20_fails_if_not_whitelisted() {
         channel: send_channel.to_string(),
         remote_address: "foreign-address".to_string(),
         timeout: Some(7777),
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
     };
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: "my-account".into(),
@@ -169,3 +206,2206 @@ fn execute_cw20_fails_if_not_whitelisted() {
     let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
     assert_eq!(err, ContractError::NotOnAllowList);
 }
+
+#[test]
+fn native_transfer_mode_uses_transfer_module() {
+    let native_channel = "channel-88";
+    let mut deps = setup(&["channel-3"], &["my-token"]);
+
+    // only the owner can switch modes
+    let msg = ExecuteMsg::SetChannelMode {
+        channel: native_channel.to_string(),
+        mode: ChannelMode::NativeTransfer,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("foobar", &[]), msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    // a channel of our own port cannot be handed to the transfer module
+    let msg = ExecuteMsg::SetChannelMode {
+        channel: "channel-3".to_string(),
+        mode: ChannelMode::NativeTransfer,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::PortalChannel { id: "channel-3".to_string() });
+
+    let raw = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ChannelMode {
+            id: native_channel.to_string(),
+        },
+    )
+    .unwrap();
+    let mode_res: ChannelModeResponse = from_binary(&raw).unwrap();
+    assert_eq!(mode_res.mode, ChannelMode::NativeTransfer);
+
+    let transfer = TransferMsg {
+        channel: native_channel.to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let msg = ExecuteMsg::Transfer(transfer.clone());
+    let info = mock_info("foobar", &coins(1234567, "ucosm"));
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(1, res.messages.len());
+    let expected_timeout = mock_env().block.time.plus_seconds(DEFAULT_TIMEOUT);
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Ibc(IbcMsg::Transfer {
+            channel_id: native_channel.to_string(),
+            to_address: "foreign-address".to_string(),
+            amount: coin(1234567, "ucosm"),
+            timeout: expected_timeout.into(),
+        })
+    );
+
+    // no local escrow is recorded for the transfer module's channel
+    assert!(CHANNEL_STATE
+        .may_load(&deps.storage, (native_channel, "ucosm"))
+        .unwrap()
+        .is_none());
+
+    // cw20 tokens cannot go through the transfer module
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "my-account".into(),
+        amount: Uint128::new(888777666),
+        msg: to_binary(&transfer).unwrap(),
+    });
+    let err = execute(deps.as_mut(), mock_env(), mock_info("my-token", &[]), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::OnlyNativeOnChannel {
+            id: native_channel.to_string()
+        }
+    );
+}
+
+#[test]
+fn native_transfers_stay_locked_until_resolved() {
+    let native_channel = "channel-88";
+    let mut deps = setup(&["channel-3"], &[]);
+    let msg = ExecuteMsg::SetChannelMode { channel: native_channel.to_string(), mode: ChannelMode::NativeTransfer };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let transfer = |deps: &mut OwnedDeps<_, _, _>, amount: u128| {
+        let msg = ExecuteMsg::Transfer(TransferMsg {
+            channel: native_channel.to_string(),
+            remote_address: "foreign-address".to_string(),
+            ..TransferMsg::default()
+        });
+        let res = execute(deps.as_mut(), mock_env(), mock_info("foobar", &coins(amount, "ucosm")), msg).unwrap();
+        // the transfer module reports the sequence in its send_packet event
+        let events = vec![Event::new("send_packet").add_attribute("packet_sequence", amount.to_string())];
+        let result = ContractResult::Ok(SubMsgExecutionResponse { events, data: None });
+        reply(deps.as_mut(), mock_env(), Reply { id: res.messages[0].id, result }).unwrap();
+    };
+    transfer(&mut deps, 1000);
+    transfer(&mut deps, 200);
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::NativeTransfers { start_after: None, limit: None }).unwrap();
+    let res: NativeTransfersResponse = from_binary(&raw).unwrap();
+    assert_eq!(res.transfers.len(), 2);
+    assert_eq!(res.transfers[0].sender, Addr::unchecked("foobar"));
+    assert_eq!(res.transfers[0].amount, coin(1000, "ucosm"));
+    assert_eq!(res.transfers[0].sequence, Some(1000));
+    assert_eq!(res.transfers[1].sequence, Some(200));
+
+    // refunds come back to the contract as the ICS-20 sender, the treasury can't spend them
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(1_000, "ucosm"));
+    let spend = ExecuteMsg::TreasuryTransfer {
+        amount: Amount::Native(coin(1, "ucosm")),
+        channel: "channel-3".to_string(),
+        remote_address: "dao-treasury".to_string(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), spend.clone()).unwrap_err();
+    assert_eq!(err, ContractError::InsufficientTreasury { available: Uint128::zero() });
+
+    let resolve = |id: u64, resolution: NativeTransferResolution| ExecuteMsg::ResolveNativeTransfer { id, resolution };
+    let msg = resolve(1, NativeTransferResolution::Refunded {});
+    let err = execute(deps.as_mut(), mock_env(), mock_info("foobar", &[]), msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send { to_address: "foobar".into(), amount: coins(1000, "ucosm") })
+    );
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::NoSuchNativeTransfer { id: 1 });
+
+    // a delivered transfer only releases the lock
+    let msg = resolve(2, NativeTransferResolution::Delivered {});
+    let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    assert!(res.messages.is_empty());
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(1, "ucosm"));
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), spend).unwrap();
+}
+
+#[test]
+fn owner_manages_params() {
+    let mut deps = setup(&["channel-3"], &[]);
+
+    let msg = ExecuteMsg::SetParam {
+        key: "batch_size".to_string(),
+        value: ParamValue::U64(25),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("foobar", &[]), msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SetParam {
+        key: "grace_enabled".to_string(),
+        value: ParamValue::Bool(true),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    assert_eq!(param_u64(&deps.storage, "batch_size").unwrap(), Some(25));
+    assert_eq!(param_u64(&deps.storage, "missing").unwrap(), None);
+    // reading with the wrong type is an error rather than a silent default
+    param_bool(&deps.storage, "batch_size").unwrap_err();
+
+    let query_page = |deps: Deps, start_after: Option<String>| -> Vec<ParamInfo> {
+        let raw = query(deps, mock_env(), QueryMsg::Params { start_after, limit: Some(1) }).unwrap();
+        let res: ParamsResponse = from_binary(&raw).unwrap();
+        res.params
+    };
+    let page = query_page(deps.as_ref(), None);
+    assert_eq!(
+        page,
+        vec![ParamInfo {
+            key: "batch_size".to_string(),
+            value: ParamValue::U64(25)
+        }]
+    );
+    let page = query_page(deps.as_ref(), Some("batch_size".to_string()));
+    assert_eq!(page[0].key, "grace_enabled");
+
+    let msg = ExecuteMsg::UnsetParam {
+        key: "batch_size".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    assert_eq!(param_u64(&deps.storage, "batch_size").unwrap(), None);
+}
+
+#[test]
+fn guardian_pause_expires_owner_pause_does_not() {
+    let send_channel = "channel-5";
+    let mut deps = setup(&[send_channel], &[]);
+    let owner = mock_info("anyone", &[]);
+
+    for (key, value) in [
+        ("guardian", ParamValue::Addr(Addr::unchecked("guardian"))),
+        ("guardian_pause_seconds", ParamValue::U64(600)),
+    ] {
+        let msg = ExecuteMsg::SetParam {
+            key: key.to_string(),
+            value,
+        };
+        execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap();
+    }
+
+    let transfer = ExecuteMsg::Transfer(TransferMsg {
+        channel: send_channel.to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    });
+    let funds = mock_info("foobar", &coins(1234567, "ucosm"));
+
+    // random accounts cannot pause
+    let err = execute(deps.as_mut(), mock_env(), mock_info("foobar", &[]), ExecuteMsg::Pause {}).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // guardian pause halts transfers, then lifts by itself
+    execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), ExecuteMsg::Pause {}).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), funds.clone(), transfer.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+
+    let mut later = mock_env();
+    later.block.time = later.block.time.plus_seconds(600);
+    let raw = query(deps.as_ref(), later.clone(), QueryMsg::Pause {}).unwrap();
+    let pause: PauseResponse = from_binary(&raw).unwrap();
+    assert!(!pause.paused);
+    execute(deps.as_mut(), later.clone(), funds.clone(), transfer.clone()).unwrap();
+
+    // owner pause stays until unpaused, and the guardian cannot override it
+    execute(deps.as_mut(), mock_env(), owner.clone(), ExecuteMsg::Pause {}).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), ExecuteMsg::Pause {}).unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+    let err = execute(deps.as_mut(), later.clone(), funds.clone(), transfer.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+
+    execute(deps.as_mut(), later.clone(), owner.clone(), ExecuteMsg::Unpause {}).unwrap();
+    execute(deps.as_mut(), later.clone(), funds.clone(), transfer.clone()).unwrap();
+
+    // without a duration a guardian pause still lifts after a day
+    let msg = ExecuteMsg::UnsetParam {
+        key: "guardian_pause_seconds".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), owner, msg).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), ExecuteMsg::Pause {}).unwrap();
+    let mut next_day = mock_env();
+    next_day.block.time = next_day.block.time.plus_seconds(24 * 60 * 60);
+    let err = execute(deps.as_mut(), later, funds.clone(), transfer.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+    execute(deps.as_mut(), next_day.clone(), funds.clone(), transfer.clone()).unwrap();
+
+    // an absurd duration saturates instead of overflowing
+    let msg = ExecuteMsg::SetParam {
+        key: "guardian_pause_seconds".to_string(),
+        value: ParamValue::U64(u64::MAX),
+    };
+    execute(deps.as_mut(), next_day.clone(), mock_info("anyone", &[]), msg).unwrap();
+    execute(deps.as_mut(), next_day.clone(), mock_info("guardian", &[]), ExecuteMsg::Pause {}).unwrap();
+    let err = execute(deps.as_mut(), next_day, funds, transfer).unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+}
+
+#[test]
+fn denom_alias_resolves_per_channel() {
+    let mut deps = setup(&["channel-3", "channel-7"], &["my-token"]);
+
+    let msg = ExecuteMsg::SetDenomAlias {
+        channel: "channel-33".to_string(),
+        alias: "usdc".to_string(),
+        denom: "uusdc".to_string(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::NoSuchChannel { id: "channel-33".to_string() });
+    let msg = ExecuteMsg::SetDenomAlias {
+        channel: "channel-3".to_string(),
+        alias: "usdc".to_string(),
+        denom: "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    let msg = ExecuteMsg::SetDenomAlias {
+        channel: "channel-7".to_string(),
+        alias: "usdc".to_string(),
+        denom: "cw20:my-token".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let raw = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ResolveDenom {
+            channel: "channel-7".to_string(),
+            alias: "usdc".to_string(),
+        },
+    )
+    .unwrap();
+    let res: ResolveDenomResponse = from_binary(&raw).unwrap();
+    assert_eq!(
+        res,
+        ResolveDenomResponse {
+            denom: "cw20:my-token".to_string(),
+            is_alias: true
+        }
+    );
+
+    // the alias for the chosen channel has to match the attached funds
+    let transfer = TransferMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: Some("usdc".to_string()),
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let info = mock_info("foobar", &coins(1234567, "ucosm"));
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer.clone())).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::DenomMismatch {
+            sent: "ucosm".to_string(),
+            expected: "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2".to_string(),
+        }
+    );
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "my-account".into(),
+        amount: Uint128::new(888),
+        msg: to_binary(&TransferMsg {
+            channel: "channel-7".to_string(),
+            ..transfer
+        })
+        .unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("my-token", &[]), msg).unwrap();
+}
+
+fn sent_packet(res: &Response) -> Ics20Packet {
+    let send = res
+        .messages
+        .iter()
+        .find_map(|m| match &m.msg {
+            CosmosMsg::Ibc(IbcMsg::SendPacket { data, .. }) => Some(data),
+            _ => None,
+        })
+        .expect("no packet sent");
+    from_binary(send).unwrap()
+}
+
+#[test]
+fn bps_fee_is_collected_and_distributed() {
+    let send_channel = "channel-5";
+    let mut deps = setup(&[send_channel], &[]);
+
+    let msg = ExecuteMsg::SetFeeConfig {
+        fee_config: Some(FeeConfigMsg {
+            bps: 30,
+            collector: "treasury".to_string(),
+            fee_denom: None,
+            referrer_bps: 0,
+            collectors: vec![],
+        }),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let transfer = TransferMsg {
+        channel: send_channel.to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let info = mock_info("foobar", &coins(1_000_000, "ucosm"));
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer)).unwrap();
+    assert_eq!(sent_packet(&res).amount, Uint128::new(997_000));
+
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::CollectedFees {}).unwrap();
+    let fees: CollectedFeesResponse = from_binary(&raw).unwrap();
+    assert_eq!(fees.fees, vec![Amount::Native(coin(3_000, "ucosm"))]);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("random", &[]), ExecuteMsg::DistributeFees {}).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: coins(3_000, "ucosm"),
+        })
+    );
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::CollectedFees {}).unwrap();
+    let fees: CollectedFeesResponse = from_binary(&raw).unwrap();
+    assert_eq!(fees.fees, vec![]);
+    // lifetime metrics still count the distributed fees
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Metrics {}).unwrap();
+    let metrics: MetricsResponse = from_binary(&raw).unwrap();
+    assert_eq!(metrics.fees_collected, vec![Amount::from_parts("ucosm".to_string(), Uint128::new(3_000))]);
+}
+
+#[test]
+fn removing_fee_config_pays_out_collected_fees() {
+    let send_channel = "channel-5";
+    let mut deps = setup(&[send_channel], &[]);
+
+    let msg = ExecuteMsg::SetFeeConfig {
+        fee_config: Some(FeeConfigMsg {
+            bps: 30,
+            collector: "treasury".to_string(),
+            fee_denom: None,
+            referrer_bps: 0,
+            collectors: vec![],
+        }),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let transfer = TransferMsg {
+        channel: send_channel.to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let info = mock_info("foobar", &coins(1_000_000, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer)).unwrap();
+
+    // the collected fees would be stranded without a config to distribute them
+    let msg = ExecuteMsg::SetFeeConfig { fee_config: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: coins(3_000, "ucosm"),
+        })
+    );
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::CollectedFees {}).unwrap();
+    let fees: CollectedFeesResponse = from_binary(&raw).unwrap();
+    assert_eq!(fees.fees, vec![]);
+    let err = execute(deps.as_mut(), mock_env(), mock_info("random", &[]), ExecuteMsg::DistributeFees {}).unwrap_err();
+    assert_eq!(err, ContractError::NoFeeConfig {});
+}
+
+#[test]
+fn fees_split_among_weighted_collectors() {
+    let send_channel = "channel-5";
+    let mut deps = setup(&[send_channel], &[]);
+
+    let collector = |address: &str, weight: u64| FeeCollectorMsg { address: address.to_string(), weight };
+    let set_fees = |collectors: Vec<FeeCollectorMsg>| ExecuteMsg::SetFeeConfig {
+        fee_config: Some(FeeConfigMsg {
+            bps: 30,
+            collector: "treasury".to_string(),
+            fee_denom: None,
+            referrer_bps: 0,
+            collectors,
+        }),
+    };
+    let msg = set_fees(vec![collector("dev-fund", 0)]);
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidCollectorWeight {});
+    let collectors = vec![collector("treasury", 5), collector("dev-fund", 3), collector("insurance", 3)];
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), set_fees(collectors)).unwrap();
+
+    let transfer = TransferMsg {
+        channel: send_channel.to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let info = mock_info("foobar", &coins(1_000_000, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer)).unwrap();
+
+    // 3_000 split 5:3:3, the first collector gets the rounding dust
+    let res = execute(deps.as_mut(), mock_env(), mock_info("random", &[]), ExecuteMsg::DistributeFees {}).unwrap();
+    let payouts: Vec<_> = res.messages.iter().map(|m| m.msg.clone()).collect();
+    let send = |to: &str, amount: u128| {
+        CosmosMsg::Bank(BankMsg::Send { to_address: to.to_string(), amount: coins(amount, "ucosm") })
+    };
+    assert_eq!(payouts, vec![send("treasury", 1_364), send("dev-fund", 818), send("insurance", 818)]);
+}
+
+#[test]
+fn fee_tiers_apply_by_transfer_size() {
+    let send_channel = "channel-5";
+    let mut deps = setup(&[send_channel], &[]);
+    let owner = mock_info("anyone", &[]);
+
+    let msg = ExecuteMsg::SetFeeConfig {
+        fee_config: Some(FeeConfigMsg {
+            bps: 30,
+            collector: "treasury".to_string(),
+            fee_denom: None,
+            referrer_bps: 0,
+            collectors: vec![],
+        }),
+    };
+    execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap();
+
+    let tiers = vec![
+        FeeTier { min_amount: Uint128::new(1_000), fee: TierFee::Flat(Uint128::new(5)) },
+        FeeTier { min_amount: Uint128::new(100_000), fee: TierFee::Bps(10) },
+    ];
+    // brackets must be sorted
+    let msg = ExecuteMsg::SetFeeTiers { denom: "ucosm".to_string(), tiers: tiers.iter().rev().cloned().collect() };
+    let err = execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidFeeTiers {});
+    let msg = ExecuteMsg::SetFeeTiers { denom: "ucosm".to_string(), tiers: tiers.clone() };
+    execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap();
+
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::FeeTiers { denom: "ucosm".to_string() }).unwrap();
+    let res: FeeTiersResponse = from_binary(&raw).unwrap();
+    assert_eq!(res.tiers, tiers);
+
+    let transfer = TransferMsg {
+        channel: send_channel.to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let send = |deps: &mut OwnedDeps<_, _, _>, amount: u128| {
+        let info = mock_info("foobar", &coins(amount, "ucosm"));
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer.clone())).unwrap()
+    };
+    let tier_of = |res: &Response| {
+        res.events[0].attributes.iter().find(|a| a.key == "fee_tier").map(|a| a.value.clone())
+    };
+
+    // below the first bracket the flat bps applies
+    let res = send(&mut deps, 500);
+    assert_eq!(sent_packet(&res).amount, Uint128::new(499));
+    assert_eq!(tier_of(&res), None);
+    let res = send(&mut deps, 2_000);
+    assert_eq!(sent_packet(&res).amount, Uint128::new(1_995));
+    assert_eq!(tier_of(&res), Some("0".to_string()));
+    let res = send(&mut deps, 1_000_000);
+    assert_eq!(sent_packet(&res).amount, Uint128::new(999_000));
+    assert_eq!(tier_of(&res), Some("1".to_string()));
+
+    // a flat fee must leave something to bridge
+    let tiny = mock_info("foobar", &coins(1_000, "ucosm"));
+    let tiers = vec![FeeTier { min_amount: Uint128::new(1), fee: TierFee::Flat(Uint128::new(1_000)) }];
+    let msg = ExecuteMsg::SetFeeTiers { denom: "ucosm".to_string(), tiers };
+    execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), tiny, ExecuteMsg::Transfer(transfer.clone())).unwrap_err();
+    assert_eq!(err, ContractError::FeeExceedsAmount { fee: Uint128::new(1_000), amount: Uint128::new(1_000) });
+
+    // an empty schedule goes back to the bps
+    let msg = ExecuteMsg::SetFeeTiers { denom: "ucosm".to_string(), tiers: vec![] };
+    execute(deps.as_mut(), mock_env(), owner, msg).unwrap();
+    let res = send(&mut deps, 1_000_000);
+    assert_eq!(sent_packet(&res).amount, Uint128::new(997_000));
+}
+
+#[test]
+fn referrer_claims_share_of_fee() {
+    let send_channel = "channel-5";
+    let mut deps = setup(&[send_channel], &[]);
+
+    let msg = ExecuteMsg::SetFeeConfig {
+        fee_config: Some(FeeConfigMsg {
+            bps: 30,
+            collector: "treasury".to_string(),
+            fee_denom: None,
+            referrer_bps: 2_000,
+            collectors: vec![],
+        }),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let transfer = TransferMsg {
+        channel: send_channel.to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: Some("wallet".to_string()),
+        route: None,
+        timeout_duration: None,
+    };
+    let info = mock_info("wallet", &coins(1_000_000, "ucosm"));
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer.clone())).unwrap_err();
+    assert_eq!(err, ContractError::SelfReferral {});
+    let info = mock_info("foobar", &coins(1_000_000, "ucosm"));
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer)).unwrap();
+    assert_eq!(sent_packet(&res).amount, Uint128::new(997_000));
+
+    // a fifth of the 3_000 fee goes to the referrer, the rest to the collector
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::CollectedFees {}).unwrap();
+    let fees: CollectedFeesResponse = from_binary(&raw).unwrap();
+    assert_eq!(fees.fees, vec![Amount::Native(coin(2_400, "ucosm"))]);
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::ReferralFees { referrer: "wallet".to_string() }).unwrap();
+    let referral: ReferralFeesResponse = from_binary(&raw).unwrap();
+    assert_eq!(referral.fees, vec![Amount::Native(coin(600, "ucosm"))]);
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("someone", &[]), ExecuteMsg::ClaimReferralFees {}).unwrap_err();
+    assert_eq!(err, ContractError::NoReferralFees {});
+    let res = execute(deps.as_mut(), mock_env(), mock_info("wallet", &[]), ExecuteMsg::ClaimReferralFees {}).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "wallet".to_string(),
+            amount: coins(600, "ucosm"),
+        })
+    );
+    let err = execute(deps.as_mut(), mock_env(), mock_info("wallet", &[]), ExecuteMsg::ClaimReferralFees {}).unwrap_err();
+    assert_eq!(err, ContractError::NoReferralFees {});
+}
+
+#[test]
+fn treasury_transfer_spends_only_free_balance() {
+    let send_channel = "channel-5";
+    let mut deps = setup(&[send_channel], &[]);
+    let msg = ExecuteMsg::SetFeeConfig {
+        fee_config: Some(FeeConfigMsg {
+            bps: 30,
+            collector: "treasury".to_string(),
+            fee_denom: None,
+            referrer_bps: 0,
+            collectors: vec![],
+        }),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    // 1000 of the contract's ucosm is escrow for a user transfer and its fee
+    let transfer = TransferMsg {
+        channel: send_channel.to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let info = mock_info("foobar", &coins(1_000, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer)).unwrap();
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(1_500, "ucosm"));
+
+    let spend = |amount: u128| ExecuteMsg::TreasuryTransfer {
+        amount: Amount::Native(coin(amount, "ucosm")),
+        channel: send_channel.to_string(),
+        remote_address: "dao-treasury".to_string(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("intruder", &[]), spend(100)).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), spend(600)).unwrap_err();
+    assert_eq!(err, ContractError::InsufficientTreasury { available: Uint128::new(500) });
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), spend(500)).unwrap();
+    let packet = sent_packet(&res);
+    assert_eq!(packet.sender, MOCK_CONTRACT_ADDR);
+    assert_eq!(packet.receiver, "dao-treasury");
+    // the contract's own sends pay no protocol fee
+    assert_eq!(packet.amount, Uint128::new(500));
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::CollectedFees {}).unwrap();
+    let fees: CollectedFeesResponse = from_binary(&raw).unwrap();
+    assert_eq!(fees.fees, vec![Amount::Native(coin(3, "ucosm"))]);
+
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::TreasuryLog { start_after: None, limit: None }).unwrap();
+    let log: TreasuryLogResponse = from_binary(&raw).unwrap();
+    assert_eq!(
+        log.entries,
+        vec![TreasuryEntry {
+            id: 1,
+            amount: Amount::Native(coin(500, "ucosm")),
+            channel: send_channel.to_string(),
+            remote_address: "dao-treasury".to_string(),
+            sent_by: Addr::unchecked("anyone"),
+            height: mock_env().block.height,
+        }]
+    );
+}
+
+#[test]
+fn treasury_excludes_user_escrow() {
+    let send_channel = "channel-5";
+    let mut deps = setup(&[send_channel], &[]);
+
+    let schedule = ScheduleMsg {
+        channel: send_channel.to_string(),
+        remote_address: "foreign-address".to_string(),
+        amount: Uint128::new(100),
+        interval: 60,
+        start_time: None,
+        end: None,
+        timeout: None,
+        timeout_duration: None,
+    };
+    let info = mock_info("payer", &coins(400, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::ScheduleTransfer(schedule)).unwrap();
+    let lock = LockHtlcMsg {
+        hash: hex::encode(Sha256::digest(b"secret")),
+        recipient: "bob".to_string(),
+        expires: Expiration::AtHeight(mock_env().block.height + 10),
+    };
+    let info = mock_info("alice", &coins(300, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::LockHtlc(lock)).unwrap();
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(1_000, "ucosm"));
+
+    let spend = |amount: u128| ExecuteMsg::TreasuryTransfer {
+        amount: Amount::Native(coin(amount, "ucosm")),
+        channel: send_channel.to_string(),
+        remote_address: "dao-treasury".to_string(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), spend(301)).unwrap_err();
+    assert_eq!(err, ContractError::InsufficientTreasury { available: Uint128::new(300) });
+
+    // the first run moves 100 from the schedule into the channel escrow, the claim pays out 300
+    let msg = ExecuteMsg::RunScheduled { limit: None };
+    execute(deps.as_mut(), mock_env(), mock_info("cranker", &[]), msg).unwrap();
+    let msg = ExecuteMsg::ClaimHtlc { preimage: hex::encode(b"secret"), id: None };
+    execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap();
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(700, "ucosm"));
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), spend(301)).unwrap_err();
+    assert_eq!(err, ContractError::InsufficientTreasury { available: Uint128::new(300) });
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), spend(300)).unwrap();
+}
+
+#[test]
+fn fee_paid_in_fee_denom_keeps_amount_whole() {
+    let send_channel = "channel-5";
+    let mut deps = setup(&[send_channel], &["my-token"]);
+
+    let set_fee_denom = |denom: &str| ExecuteMsg::SetFeeConfig {
+        fee_config: Some(FeeConfigMsg {
+            bps: 30,
+            collector: "treasury".to_string(),
+            fee_denom: Some(FlatFee {
+                denom: denom.to_string(),
+                amount: Uint128::new(50),
+            }),
+            referrer_bps: 0,
+            collectors: vec![],
+        }),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), set_fee_denom("ufee")).unwrap();
+
+    let transfer = TransferMsg {
+        channel: send_channel.to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: true,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+
+    // native fee coin attached next to the bridged coin
+    let info = mock_info("foobar", &[coin(1_000_000, "ucosm"), coin(50, "ufee")]);
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer.clone())).unwrap();
+    assert_eq!(sent_packet(&res).amount, Uint128::new(1_000_000));
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::CollectedFees {}).unwrap();
+    let fees: CollectedFeesResponse = from_binary(&raw).unwrap();
+    assert_eq!(fees.fees, vec![Amount::Native(coin(50, "ufee"))]);
+
+    // missing or short fee coin is rejected
+    let info = mock_info("foobar", &[coin(1_000_000, "ucosm"), coin(49, "ufee")]);
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer.clone())).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidFeePayment {
+            expected: "50ufee".to_string()
+        }
+    );
+
+    // a cw20 fee denom is pulled from the sender's allowance in the same transaction
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), set_fee_denom("cw20:fee-token")).unwrap();
+    let info = mock_info("foobar", &coins(1_000_000, "ucosm"));
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer)).unwrap();
+    assert_eq!(2, res.messages.len());
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "fee-token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: "foobar".to_string(),
+                recipient: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::new(50),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert_eq!(sent_packet(&res).amount, Uint128::new(1_000_000));
+}
+
+#[test]
+fn token_metadata_registry() {
+    let mut deps = setup(&["channel-3"], &["my-token"]);
+    deps.querier.update_smart("my-token", |msg| {
+        let res = match from_binary(msg).unwrap() {
+            Cw20QueryMsg::TokenInfo {} => to_binary(&TokenInfoResponse {
+                name: "My Token".to_string(),
+                symbol: "MYT".to_string(),
+                decimals: 6,
+                total_supply: Uint128::new(1_000_000),
+            }),
+            Cw20QueryMsg::MarketingInfo {} => to_binary(&MarketingInfoResponse {
+                project: None,
+                description: None,
+                logo: Some(LogoInfo::Url("https://example.com/myt.png".to_string())),
+                marketing: None,
+            }),
+            _ => panic!("unexpected query"),
+        };
+        ContractResult::Ok(res.unwrap())
+    });
+
+    // anyone can sync a whitelisted cw20 from its own queries
+    let msg = ExecuteMsg::SyncTokenMetadata {
+        contract: "my-token".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("random", &[]), msg).unwrap();
+    let msg = ExecuteMsg::SyncTokenMetadata {
+        contract: "other-token".to_string(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("random", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::NotOnAllowList);
+
+    // native denoms are registered by the owner
+    let atom = TokenMetadata {
+        symbol: "ATOM".to_string(),
+        decimals: 6,
+        logo: None,
+    };
+    let msg = ExecuteMsg::SetTokenMetadata {
+        denom: "uatom".to_string(),
+        metadata: atom.clone(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("random", &[]), msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let raw = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::TokenMetadata {
+            denom: "cw20:my-token".to_string(),
+        },
+    )
+    .unwrap();
+    let res: TokenMetadataResponse = from_binary(&raw).unwrap();
+    assert_eq!(
+        res.metadata,
+        Some(TokenMetadata {
+            symbol: "MYT".to_string(),
+            decimals: 6,
+            logo: Some("https://example.com/myt.png".to_string()),
+        })
+    );
+
+    let raw = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ListTokenMetadata {
+            start_after: Some("cw20:my-token".to_string()),
+            limit: None,
+        },
+    )
+    .unwrap();
+    let res: ListTokenMetadataResponse = from_binary(&raw).unwrap();
+    assert_eq!(
+        res.tokens,
+        vec![TokenMetadataResponse {
+            denom: "uatom".to_string(),
+            metadata: Some(atom),
+        }]
+    );
+}
+
+#[test]
+fn channel_allowlist_narrows_global_whitelist() {
+    let mut deps = setup(&["channel-3", "channel-7"], &["my-token", "other-token"]);
+    let owner = mock_info("anyone", &[]);
+
+    // a mistyped channel is refused rather than configured
+    let msg = ExecuteMsg::SetChannelAllowlist { channel: "channel-33".to_string(), enabled: true };
+    let err = execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::NoSuchChannel { id: "channel-33".to_string() });
+    let msg = ExecuteMsg::UpdateChannelAllowlist {
+        channel: "channel-33".to_string(),
+        add: vec!["ucosm".to_string()],
+        remove: vec![],
+    };
+    let err = execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::NoSuchChannel { id: "channel-33".to_string() });
+
+    let msg = ExecuteMsg::UpdateChannelAllowlist {
+        channel: "channel-3".to_string(),
+        add: vec!["cw20:my-token".to_string(), "ucosm".to_string()],
+        remove: vec![],
+    };
+    execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap();
+    let msg = ExecuteMsg::SetChannelAllowlist {
+        channel: "channel-3".to_string(),
+        enabled: true,
+    };
+    execute(deps.as_mut(), mock_env(), owner, msg).unwrap();
+
+    let cw20_transfer = |token: &str, channel: &str| {
+        let transfer = TransferMsg {
+            channel: channel.to_string(),
+            remote_address: "foreign-address".to_string(),
+            timeout: None,
+            denom: None,
+            fee_in_fee_denom: false,
+            memo: None,
+            referrer: None,
+            route: None,
+            timeout_duration: None,
+        };
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "my-account".into(),
+            amount: Uint128::new(100),
+            msg: to_binary(&transfer).unwrap(),
+        });
+        (mock_info(token, &[]), msg)
+    };
+
+    let (info, msg) = cw20_transfer("my-token", "channel-3");
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let (info, msg) = cw20_transfer("other-token", "channel-3");
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NotAllowedOnChannel {
+            denom: "cw20:other-token".to_string(),
+            channel: "channel-3".to_string(),
+        }
+    );
+    // other channels only apply the global whitelist
+    let (info, msg) = cw20_transfer("other-token", "channel-7");
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let raw = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ChannelAllowlist {
+            channel: "channel-3".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let res: ChannelAllowlistResponse = from_binary(&raw).unwrap();
+    assert!(res.enabled);
+    assert_eq!(res.tokens, vec!["cw20:my-token".to_string(), "ucosm".to_string()]);
+}
+
+#[test]
+fn verify_remote_address_reports_issues() {
+    let mut deps = setup(&["channel-3"], &[]);
+    let verify = |deps: &OwnedDeps<_, _, _>, channel: &str, address: &str| {
+        let msg = QueryMsg::VerifyRemoteAddress { channel: channel.to_string(), address: address.to_string() };
+        from_binary::<VerifyRemoteAddressResponse>(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+    };
+    let valid = "osmo1qqqsyqcyq5rqwzqfpg9scrgwpugpzysntdz28t";
+
+    // without a configured prefix any valid bech32 account passes
+    let res = verify(&deps, "channel-3", valid);
+    assert!(res.valid);
+    assert_eq!(res.expected_prefix, None);
+
+    let msg = ExecuteMsg::SetRemotePrefix { channel: "channel-3".to_string(), prefix: Some("cosmos".to_string()) };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    let res = verify(&deps, "channel-3", valid);
+    assert!(!res.valid);
+    assert_eq!(
+        res.issues,
+        vec![AddressIssue::WrongPrefix { expected: "cosmos".to_string(), found: "osmo".to_string() }]
+    );
+
+    let msg = ExecuteMsg::SetRemotePrefix { channel: "channel-3".to_string(), prefix: Some("osmo".to_string()) };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    assert!(verify(&deps, "channel-3", valid).valid);
+    // a typo breaks the checksum
+    let res = verify(&deps, "channel-3", "osmo1qqqsyqcyq5rqwzqfpg9scrgwpugpzysntdz28q");
+    assert_eq!(res.issues, vec![AddressIssue::InvalidChecksum {}]);
+    // a well formed but short payload
+    let res = verify(&deps, "channel-3", "osmo1qqqsyqcyq5rqwzqft8el69");
+    assert_eq!(res.issues, vec![AddressIssue::InvalidPayloadLength { length: 10 }]);
+    let res = verify(&deps, "channel-3", "OSMO1qqqsyqcyq5rqwzqfpg9scrgwpugpzysntdz28t");
+    assert_eq!(res.issues, vec![AddressIssue::MixedCase {}]);
+    let res = verify(&deps, "channel-3", "osmo1qqqsyqcyq5rqwzqfpg9scrgwpugpzysntdz28b");
+    assert_eq!(res.issues, vec![AddressIssue::InvalidCharacter { character: "b".to_string() }]);
+    let res = verify(&deps, "channel-9", "foreign-address");
+    assert_eq!(res.issues, vec![AddressIssue::UnknownChannel {}, AddressIssue::NoSeparator {}]);
+}
+
+#[test]
+fn native_allowlist_blocks_unlisted_denoms() {
+    let mut deps = setup(&["channel-3"], &[]);
+    let owner = mock_info("anyone", &[]);
+
+    let transfer = TransferMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let send = |denom: &str| {
+        let info = mock_info("my-account", &coins(100, denom));
+        (info, ExecuteMsg::Transfer(transfer.clone()))
+    };
+
+    // disabled by default, so spam denoms go through
+    let (info, msg) = send("ibc/DEADBEEF");
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // only the owner manages the list
+    let msg = ExecuteMsg::SetNativeAllowlist { enabled: true };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("intruder", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let msg = ExecuteMsg::UpdateNativeAllowlist {
+        add: vec!["ucosm".to_string(), "uatom".to_string()],
+        remove: vec![],
+    };
+    execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap();
+    let msg = ExecuteMsg::SetNativeAllowlist { enabled: true };
+    execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap();
+
+    let (info, msg) = send("ucosm");
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let (info, msg) = send("ibc/DEADBEEF");
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NativeNotAllowed {
+            denom: "ibc/DEADBEEF".to_string()
+        }
+    );
+
+    let msg = ExecuteMsg::UpdateNativeAllowlist {
+        add: vec![],
+        remove: vec!["ucosm".to_string()],
+    };
+    execute(deps.as_mut(), mock_env(), owner, msg).unwrap();
+    let (info, msg) = send("ucosm");
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+
+    let raw = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::NativeAllowlist {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let res: NativeAllowlistResponse = from_binary(&raw).unwrap();
+    assert!(res.enabled);
+    assert_eq!(res.denoms, vec!["uatom".to_string()]);
+}
+
+#[test]
+fn named_route_builds_forward_memo() {
+    let mut deps = setup(&["channel-3", "channel-7"], &[]);
+    let owner = mock_info("anyone", &[]);
+
+    let route = Route {
+        channel: "channel-3".to_string(),
+        hops: vec![
+            RouteHop {
+                receiver: HopReceiver::Placeholder {},
+                channel: "channel-750".to_string(),
+                port: None,
+                timeout: Some("10m".to_string()),
+                retries: Some(2),
+            },
+            RouteHop {
+                receiver: HopReceiver::Address { address: "osmo1forwarder".to_string() },
+                channel: "channel-1".to_string(),
+                port: None,
+                timeout: None,
+                retries: None,
+            },
+        ],
+    };
+    let msg = ExecuteMsg::SetRoute { name: "to-noble-usdc".to_string(), route: Route { hops: vec![], ..route.clone() } };
+    let err = execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidRoute {});
+    let msg = ExecuteMsg::SetRoute { name: "to-noble-usdc".to_string(), route: route.clone() };
+    execute(deps.as_mut(), mock_env(), owner, msg).unwrap();
+
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Routes { start_after: None, limit: None }).unwrap();
+    let res: RoutesResponse = from_binary(&raw).unwrap();
+    assert_eq!(res.routes, vec![RouteInfo { name: "to-noble-usdc".to_string(), route }]);
+
+    let transfer = TransferMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "noble1final".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: Some("to-noble-usdc".to_string()),
+        timeout_duration: None,
+    };
+    let info = mock_info("foobar", &coins(1234567, "ucosm"));
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::Transfer(transfer.clone())).unwrap();
+    let packet = sent_packet(&res);
+    assert_eq!(packet.receiver, "pfm");
+    assert_eq!(
+        packet.memo.unwrap(),
+        r#"{"forward":{"receiver":"osmo1forwarder","port":"transfer","channel":"channel-750","timeout":"10m","retries":2,"next":{"forward":{"receiver":"noble1final","port":"transfer","channel":"channel-1"}}}}"#
+    );
+
+    // the route decides the memo and the first channel
+    let msg = TransferMsg { memo: Some("{}".to_string()), ..transfer.clone() };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::Transfer(msg)).unwrap_err();
+    assert_eq!(err, ContractError::MemoWithRoute {});
+    let msg = TransferMsg { channel: "channel-7".to_string(), ..transfer.clone() };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::Transfer(msg)).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::RouteChannelMismatch {
+            name: "to-noble-usdc".to_string(),
+            expected: "channel-3".to_string(),
+            channel: "channel-7".to_string(),
+        }
+    );
+    let msg = TransferMsg { route: Some("nowhere".to_string()), ..transfer };
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(msg)).unwrap_err();
+    assert_eq!(err, ContractError::NoSuchRoute { name: "nowhere".to_string() });
+}
+
+#[test]
+fn scheduled_transfer_runs_until_budget_spent() {
+    let mut deps = setup(&["channel-3"], &[]);
+
+    let schedule = ScheduleMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        amount: Uint128::new(400),
+        interval: 60,
+        start_time: None,
+        end: None,
+        timeout: None,
+        timeout_duration: None,
+    };
+    // funding below one run is rejected
+    let info = mock_info("payer", &coins(300, "ucosm"));
+    let msg = ExecuteMsg::ScheduleTransfer(schedule.clone());
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidSchedule {});
+
+    let info = mock_info("payer", &coins(1000, "ucosm"));
+    let msg = ExecuteMsg::ScheduleTransfer(schedule);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let crank = |deps: &mut OwnedDeps<_, _, _>, seconds: u64| {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(seconds);
+        let msg = ExecuteMsg::RunScheduled { limit: None };
+        execute(deps.as_mut(), env, mock_info("cranker", &[]), msg).unwrap()
+    };
+
+    let res = crank(&mut deps, 0);
+    let packet = sent_packet(&res);
+    assert_eq!(packet.amount, Uint128::new(400));
+    assert_eq!(packet.sender, "payer");
+    // nothing is due until the interval has passed
+    let res = crank(&mut deps, 30);
+    assert_eq!(0, res.messages.len());
+    crank(&mut deps, 60);
+
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Schedule { id: 1 }).unwrap();
+    let stored: ScheduledTransfer = from_binary(&raw).unwrap();
+    assert_eq!(stored.runs, 2);
+    assert_eq!(stored.remaining, Uint128::new(200));
+
+    // the last run only sends what is left and removes the schedule
+    let res = crank(&mut deps, 120);
+    assert_eq!(sent_packet(&res).amount, Uint128::new(200));
+    query(deps.as_ref(), mock_env(), QueryMsg::Schedule { id: 1 }).unwrap_err();
+}
+
+#[test]
+fn escrow_timeouts_are_capped_at_creation() {
+    let mut deps = setup(&["channel-3"], &[]);
+    let mut schedule = ScheduleMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        amount: Uint128::new(400),
+        interval: 60,
+        start_time: None,
+        end: None,
+        timeout: Some(31 * 24 * 3600),
+        timeout_duration: None,
+    };
+    let info = mock_info("payer", &coins(1000, "ucosm"));
+    let msg = ExecuteMsg::ScheduleTransfer(schedule.clone());
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::TimeoutTooLong { .. }));
+
+    schedule.timeout = None;
+    schedule.timeout_duration = Some("2h".to_string());
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::ScheduleTransfer(schedule)).unwrap();
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Schedule { id: 1 }).unwrap();
+    let stored: ScheduledTransfer = from_binary(&raw).unwrap();
+    assert_eq!(stored.timeout, Some(7200));
+
+    // updates take the same formats and limits
+    let update = |timeout: Option<u64>, duration: Option<&str>| ExecuteMsg::UpdateScheduled {
+        id: 1,
+        remote_address: None,
+        amount: None,
+        interval: None,
+        end: None,
+        timeout,
+        timeout_duration: duration.map(String::from),
+    };
+    let payer = mock_info("payer", &[]);
+    let err = execute(deps.as_mut(), mock_env(), payer.clone(), update(None, Some("31d"))).unwrap_err();
+    assert!(matches!(err, ContractError::TimeoutTooLong { .. }));
+    let err = execute(deps.as_mut(), mock_env(), payer.clone(), update(Some(60), Some("1m"))).unwrap_err();
+    assert_eq!(err, ContractError::ConflictingTimeouts {});
+    execute(deps.as_mut(), mock_env(), payer, update(None, Some("3h"))).unwrap();
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Schedule { id: 1 }).unwrap();
+    let stored: ScheduledTransfer = from_binary(&raw).unwrap();
+    assert_eq!(stored.timeout, Some(10800));
+
+    let stream = StreamMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        duration: 100,
+        start_time: None,
+        timeout: None,
+        timeout_duration: Some("90d".to_string()),
+    };
+    let info = mock_info("payer", &coins(1000, "ucosm"));
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::CreateStream(stream)).unwrap_err();
+    assert!(matches!(err, ContractError::TimeoutTooLong { .. }));
+}
+
+#[test]
+fn failed_scheduled_run_keeps_no_fee() {
+    let mut deps = setup(&["channel-3"], &[]);
+    let msg = ExecuteMsg::SetFeeConfig {
+        fee_config: Some(FeeConfigMsg {
+            bps: 30,
+            collector: "treasury".to_string(),
+            fee_denom: None,
+            referrer_bps: 0,
+            collectors: vec![],
+        }),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let schedule = ScheduleMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        amount: Uint128::new(400),
+        interval: 60,
+        start_time: None,
+        end: None,
+        timeout: Some(3600),
+        timeout_duration: None,
+    };
+    let info = mock_info("payer", &coins(1000, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::ScheduleTransfer(schedule)).unwrap();
+    // the cap drops below the schedule's timeout, so its runs fail after the fee is known
+    let msg = ExecuteMsg::SetParam { key: "max_timeout".to_string(), value: ParamValue::U64(60) };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RunScheduled { limit: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("cranker", &[]), msg).unwrap();
+    assert!(res.messages.is_empty());
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::CollectedFees {}).unwrap();
+    let fees: CollectedFeesResponse = from_binary(&raw).unwrap();
+    assert_eq!(fees.fees, vec![]);
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Schedule { id: 1 }).unwrap();
+    let stored: ScheduledTransfer = from_binary(&raw).unwrap();
+    assert_eq!(stored.runs, 0);
+    assert_eq!(stored.remaining, Uint128::new(1000));
+}
+
+#[test]
+fn cw20_scheduled_transfer_refunds_after_last_run() {
+    let mut deps = setup(&["channel-3"], &["my-token"]);
+
+    let schedule = ScheduleMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        amount: Uint128::new(100),
+        interval: 60,
+        start_time: None,
+        end: Some(ScheduleEnd::AfterRuns(1)),
+        timeout: None,
+        timeout_duration: None,
+    };
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "payer".into(),
+        amount: Uint128::new(250),
+        msg: to_binary(&ReceiveMsg::ScheduleTransfer(schedule)).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("my-token", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RunScheduled { limit: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("cranker", &[]), msg).unwrap();
+    assert_eq!(2, res.messages.len());
+    assert_eq!(sent_packet(&res).amount, Uint128::new(100));
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "my-token".into(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "payer".into(),
+                amount: Uint128::new(150),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+}
+
+#[test]
+fn sender_cancels_and_updates_schedules() {
+    let mut deps = setup(&["channel-3"], &[]);
+
+    let schedule = ScheduleMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        amount: Uint128::new(100),
+        interval: 60,
+        start_time: None,
+        end: None,
+        timeout: None,
+        timeout_duration: None,
+    };
+    for _ in 0..2 {
+        let info = mock_info("payer", &coins(500, "ucosm"));
+        let msg = ExecuteMsg::ScheduleTransfer(schedule.clone());
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    let update = ExecuteMsg::UpdateScheduled {
+        id: 1,
+        remote_address: None,
+        amount: Some(Uint128::new(250)),
+        interval: None,
+        end: None,
+        timeout: None,
+        timeout_duration: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("intruder", &[]), update.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("payer", &[]), update).unwrap();
+
+    let msg = ExecuteMsg::CancelScheduled { id: 2 };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("intruder", &[]), msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = execute(deps.as_mut(), mock_env(), mock_info("payer", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "payer".into(),
+            amount: coins(500, "ucosm"),
+        })
+    );
+
+    let raw = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::SchedulesBySender {
+            sender: "payer".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let res: SchedulesResponse = from_binary(&raw).unwrap();
+    assert_eq!(1, res.schedules.len());
+    assert_eq!(res.schedules[0].id, 1);
+    assert_eq!(res.schedules[0].amount, Uint128::new(250));
+
+    // the cancelled schedule is not run by the crank
+    let msg = ExecuteMsg::RunScheduled { limit: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("cranker", &[]), msg).unwrap();
+    assert_eq!(1, res.messages.len());
+    assert_eq!(sent_packet(&res).amount, Uint128::new(250));
+}
+
+#[test]
+fn htlc_claims_with_preimage_or_refunds_after_expiry() {
+    let mut deps = setup(&[], &["my-token"]);
+    let hash = hex::encode(Sha256::digest(b"secret"));
+    let expires = Expiration::AtHeight(mock_env().block.height + 10);
+
+    let lock = LockHtlcMsg {
+        hash: hash.clone(),
+        recipient: "bob".to_string(),
+        expires,
+    };
+    let info = mock_info("alice", &coins(100, "ucosm"));
+    let msg = ExecuteMsg::LockHtlc(lock.clone());
+    execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+    // locking dust under a hash seen elsewhere doesn't block the real lock
+    let dust = LockHtlcMsg { recipient: "mallory".to_string(), ..lock.clone() };
+    let info = mock_info("mallory", &coins(1, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::LockHtlc(dust)).unwrap();
+    let msg = QueryMsg::HtlcsByHash { hash: hash.clone(), start_after: None, limit: None };
+    let res: HtlcsResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let ids: Vec<_> = res.htlcs.iter().map(|h| h.id).collect();
+    assert_eq!(ids, vec![1, 2]);
+
+    let msg = ExecuteMsg::RefundHtlc { id: 1 };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::HtlcNotExpired {});
+    let msg = ExecuteMsg::ClaimHtlc { preimage: hex::encode(b"guess"), id: None };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::NoHtlcForHash { .. }));
+    let msg = ExecuteMsg::ClaimHtlc { preimage: hex::encode(b"secret"), id: None };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::AmbiguousHtlc { hash: hash.clone() });
+
+    let msg = ExecuteMsg::ClaimHtlc { preimage: hex::encode(b"secret"), id: Some(1) };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("anyone-else", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "bob".into(),
+            amount: coins(100, "ucosm"),
+        })
+    );
+
+    // a cw20 lock that nobody claims goes back to the sender, only listed tokens can be locked
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "alice".into(),
+        amount: Uint128::new(300),
+        msg: to_binary(&ReceiveMsg::LockHtlc(lock)).unwrap(),
+    });
+    let err = execute(deps.as_mut(), mock_env(), mock_info("unlisted-token", &[]), msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::NotOnAllowList);
+    execute(deps.as_mut(), mock_env(), mock_info("my-token", &[]), msg).unwrap();
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Htlc { id: 3 }).unwrap();
+    let htlc: Htlc = from_binary(&raw).unwrap();
+    assert_eq!(htlc.sender, Addr::unchecked("alice"));
+
+    let mut env = mock_env();
+    env.block.height += 10;
+    // expired htlcs still count, a claim without an id never looks past the second match
+    let msg = ExecuteMsg::ClaimHtlc { preimage: hex::encode(b"secret"), id: None };
+    let err = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::AmbiguousHtlc { hash: hash.clone() });
+    let msg = ExecuteMsg::ClaimHtlc { preimage: hex::encode(b"secret"), id: Some(3) };
+    let err = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::HtlcExpired {});
+    let msg = ExecuteMsg::RefundHtlc { id: 3 };
+    let res = execute(deps.as_mut(), env, mock_info("anyone-else", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "my-token".into(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "alice".into(),
+                amount: Uint128::new(300),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+}
+
+#[test]
+fn htlc_below_the_minimum_amount_is_rejected() {
+    let mut deps = setup(&[], &[]);
+    let msg = ExecuteMsg::SetParam {
+        key: "min_htlc_amount".to_string(),
+        value: ParamValue::U64(10),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let lock = LockHtlcMsg {
+        hash: hex::encode(Sha256::digest(b"secret")),
+        recipient: "bob".to_string(),
+        expires: Expiration::AtHeight(mock_env().block.height + 10),
+    };
+    let info = mock_info("mallory", &coins(9, "ucosm"));
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::LockHtlc(lock.clone())).unwrap_err();
+    assert_eq!(err, ContractError::HtlcTooSmall { amount: Uint128::new(9), min: Uint128::new(10) });
+    let info = mock_info("alice", &coins(10, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::LockHtlc(lock)).unwrap();
+}
+
+#[test]
+fn stream_releases_linearly_and_pauses() {
+    let mut deps = setup(&["channel-3"], &[]);
+
+    let stream = StreamMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        duration: 100,
+        start_time: None,
+        timeout: None,
+        timeout_duration: None,
+    };
+    let info = mock_info("payer", &coins(1000, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::CreateStream(stream)).unwrap();
+
+    let at = |seconds: u64| {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(seconds);
+        env
+    };
+    let tick = ExecuteMsg::TickStreams { start_after: None, limit: None };
+    let payer = mock_info("payer", &[]);
+
+    let res = execute(deps.as_mut(), at(25), mock_info("cranker", &[]), tick.clone()).unwrap();
+    assert_eq!(sent_packet(&res).amount, Uint128::new(250));
+    let res = execute(deps.as_mut(), at(50), mock_info("cranker", &[]), tick.clone()).unwrap();
+    assert_eq!(sent_packet(&res).amount, Uint128::new(250));
+
+    let msg = ExecuteMsg::PauseStream { id: 1 };
+    let err = execute(deps.as_mut(), at(50), mock_info("intruder", &[]), msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), at(50), payer.clone(), msg).unwrap();
+    let res = execute(deps.as_mut(), at(80), mock_info("cranker", &[]), tick.clone()).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    // resuming pushes the end back by the 30 seconds spent paused
+    execute(deps.as_mut(), at(80), payer.clone(), ExecuteMsg::ResumeStream { id: 1 }).unwrap();
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Stream { id: 1 }).unwrap();
+    let stored: Stream = from_binary(&raw).unwrap();
+    assert_eq!(stored.end, at(130).block.time);
+    let res = execute(deps.as_mut(), at(105), mock_info("cranker", &[]), tick).unwrap();
+    assert_eq!(sent_packet(&res).amount, Uint128::new(250));
+
+    let res = execute(deps.as_mut(), at(105), payer, ExecuteMsg::CancelStream { id: 1 }).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "payer".into(),
+            amount: coins(250, "ucosm"),
+        })
+    );
+    query(deps.as_ref(), mock_env(), QueryMsg::Stream { id: 1 }).unwrap_err();
+}
+
+#[test]
+fn cancelled_stream_sends_what_vested() {
+    let mut deps = setup(&["channel-3"], &[]);
+    let stream = StreamMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        duration: 100,
+        start_time: None,
+        timeout: None,
+        timeout_duration: None,
+    };
+    let info = mock_info("payer", &coins(1000, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::CreateStream(stream)).unwrap();
+
+    // 400 vested without a tick, they belong to the recipient
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(40);
+    let res = execute(deps.as_mut(), env, mock_info("payer", &[]), ExecuteMsg::CancelStream { id: 1 }).unwrap();
+    let packet = sent_packet(&res);
+    assert_eq!(packet.amount, Uint128::new(400));
+    assert_eq!(packet.receiver, "foreign-address");
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "payer".into(),
+            amount: coins(600, "ucosm"),
+        })
+    );
+
+    // while transfers are paused the vested part is refunded too
+    let info = mock_info("payer", &coins(1000, "ucosm"));
+    let stream = StreamMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        duration: 100,
+        start_time: None,
+        timeout: None,
+        timeout_duration: None,
+    };
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::CreateStream(stream.clone())).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), ExecuteMsg::Pause {}).unwrap();
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(40);
+    let res = execute(deps.as_mut(), env, mock_info("payer", &[]), ExecuteMsg::CancelStream { id: 2 }).unwrap();
+    assert_eq!(
+        res.messages.iter().map(|m| m.msg.clone()).collect::<Vec<_>>(),
+        vec![CosmosMsg::Bank(BankMsg::Send { to_address: "payer".into(), amount: coins(1000, "ucosm") })]
+    );
+    assert!(res.attributes.iter().any(|a| a.key == "transfer_error"));
+
+    // a duration past the end of time is refused instead of overflowing
+    let info = mock_info("payer", &coins(1000, "ucosm"));
+    let msg = ExecuteMsg::CreateStream(StreamMsg { duration: u64::MAX, ..stream });
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidStream {});
+}
+
+#[test]
+fn failed_stream_tick_keeps_no_fee() {
+    let mut deps = setup(&["channel-3"], &[]);
+    let msg = ExecuteMsg::SetFeeConfig {
+        fee_config: Some(FeeConfigMsg {
+            bps: 30,
+            collector: "treasury".to_string(),
+            fee_denom: None,
+            referrer_bps: 0,
+            collectors: vec![],
+        }),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let stream = StreamMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        duration: 100,
+        start_time: None,
+        timeout: Some(3600),
+        timeout_duration: None,
+    };
+    let info = mock_info("payer", &coins(1000, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::CreateStream(stream)).unwrap();
+    let msg = ExecuteMsg::SetParam { key: "max_timeout".to_string(), value: ParamValue::U64(60) };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    // every tick retries the same due amount, none of them may charge a fee
+    let tick = ExecuteMsg::TickStreams { start_after: None, limit: None };
+    for seconds in [25, 50] {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(seconds);
+        let res = execute(deps.as_mut(), env, mock_info("cranker", &[]), tick.clone()).unwrap();
+        assert!(res.messages.is_empty());
+    }
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::CollectedFees {}).unwrap();
+    let fees: CollectedFeesResponse = from_binary(&raw).unwrap();
+    assert_eq!(fees.fees, vec![]);
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Stream { id: 1 }).unwrap();
+    let stored: Stream = from_binary(&raw).unwrap();
+    assert_eq!(stored.streamed, Uint128::zero());
+}
+
+#[test]
+fn conditional_transfer_waits_for_oracle() {
+    let mut deps = setup(&["channel-3"], &[]);
+    let oracle_says = |met: bool| move |msg: &Binary| {
+        let OracleQueryMsg::CheckCondition { condition } = from_binary(msg).unwrap();
+        assert_eq!(condition, Binary::from(b"atom > 20"));
+        ContractResult::Ok(to_binary(&ConditionResponse { met }).unwrap())
+    };
+    deps.querier.update_smart("price-adapter", oracle_says(false));
+
+    let msg = ExecuteMsg::RegisterOracle {
+        name: "prices".to_string(),
+        address: "price-adapter".to_string(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("intruder", &[]), msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let conditional = ConditionalMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        oracle: "prices".to_string(),
+        condition: Binary::from(b"atom > 20"),
+        expires: Expiration::AtHeight(mock_env().block.height + 10),
+        timeout: None,
+        timeout_duration: None,
+    };
+    let info = mock_info("payer", &coins(500, "ucosm"));
+    let msg = ExecuteMsg::CreateConditional(conditional);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let trigger = ExecuteMsg::TriggerConditional { id: 1 };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone-else", &[]), trigger.clone()).unwrap_err();
+    assert_eq!(err, ContractError::ConditionNotMet {});
+    let reclaim = ExecuteMsg::ReclaimConditional { id: 1 };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("payer", &[]), reclaim).unwrap_err();
+    assert_eq!(err, ContractError::ConditionalNotExpired {});
+
+    deps.querier.update_smart("price-adapter", oracle_says(true));
+    let res = execute(deps.as_mut(), mock_env(), mock_info("anyone-else", &[]), trigger).unwrap();
+    let packet = sent_packet(&res);
+    assert_eq!(packet.amount, Uint128::new(500));
+    assert_eq!(packet.sender, "payer");
+    query(deps.as_ref(), mock_env(), QueryMsg::Conditional { id: 1 }).unwrap_err();
+}
+
+#[test]
+fn deposit_is_released_by_depositor_or_operator() {
+    let mut deps = setup(&["channel-3"], &["my-token"]);
+
+    let msg = ExecuteMsg::Deposit { operator: Some("batcher".to_string()) };
+    let info = mock_info("payer", &coins(500, "ucosm"));
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert!(res.messages.is_empty());
+    assert!(res.attributes.iter().any(|a| a.key == "deposit_id" && a.value == "1"));
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Deposit { id: 1 }).unwrap();
+    let deposit: Deposit = from_binary(&raw).unwrap();
+    assert_eq!(deposit.sender, "payer");
+    assert_eq!(deposit.operator, Some(Addr::unchecked("batcher")));
+    assert_eq!(deposit.amount, Amount::from_parts("ucosm".to_string(), Uint128::new(500)));
+    // deposits are user funds, not treasury
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(500, "ucosm"));
+    let spend = ExecuteMsg::TreasuryTransfer {
+        amount: Amount::Native(coin(1, "ucosm")),
+        channel: "channel-3".to_string(),
+        remote_address: "dao-treasury".to_string(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), spend).unwrap_err();
+    assert_eq!(err, ContractError::InsufficientTreasury { available: Uint128::zero() });
+
+    let transfer = TransferMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout_duration: Some("1h".to_string()),
+        ..Default::default()
+    };
+    let release = ExecuteMsg::Release { id: 1, msg: transfer };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("intruder", &[]), release.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let err = execute(deps.as_mut(), mock_env(), mock_info("batcher", &[]), ExecuteMsg::CancelDeposit { id: 1 }).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = execute(deps.as_mut(), mock_env(), mock_info("batcher", &[]), release.clone()).unwrap();
+    let packet = sent_packet(&res);
+    assert_eq!(packet.amount, Uint128::new(500));
+    assert_eq!(packet.sender, "payer");
+    let err = execute(deps.as_mut(), mock_env(), mock_info("payer", &[]), release).unwrap_err();
+    assert_eq!(err, ContractError::NoSuchDeposit { id: 1 });
+
+    let deposit = |amount: u128| ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "payer".into(),
+        amount: Uint128::new(amount),
+        msg: to_binary(&ReceiveMsg::Deposit { operator: None }).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("my-token", &[]), deposit(300)).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("my-token", &[]), deposit(200)).unwrap();
+    let msg = QueryMsg::DepositsBySender { sender: "payer".to_string(), start_after: None, limit: None };
+    let res: DepositsResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let ids: Vec<_> = res.deposits.iter().map(|d| d.id).collect();
+    assert_eq!(ids, vec![2, 3]);
+
+    let msg = ExecuteMsg::SetDepositOperator { id: 2, operator: Some("approver".to_string()) };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("approver", &[]), msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("payer", &[]), msg).unwrap();
+
+    let cancel = ExecuteMsg::CancelDeposit { id: 2 };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("intruder", &[]), cancel.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = execute(deps.as_mut(), mock_env(), mock_info("payer", &[]), cancel).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "my-token".into(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "payer".into(),
+                amount: Uint128::new(300),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    query(deps.as_ref(), mock_env(), QueryMsg::Deposit { id: 2 }).unwrap_err();
+    let msg = QueryMsg::DepositsBySender { sender: "payer".to_string(), start_after: None, limit: None };
+    let res: DepositsResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.deposits.len(), 1);
+}
+
+#[test]
+fn config_history_records_changes() {
+    let mut deps = setup(&[], &[]);
+    let mut env = mock_env();
+    env.block.height += 5;
+
+    let msg = ExecuteMsg::UpdateConfig {
+        default_timeout: Some(7200),
+        owner: None,
+    };
+    let err = execute(deps.as_mut(), env.clone(), mock_info("intruder", &[]), msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg).unwrap();
+    let msg = ExecuteMsg::SetParam {
+        key: "max_memo".to_string(),
+        value: ParamValue::U64(256),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg).unwrap();
+    let msg = ExecuteMsg::UnsetParam { key: "max_memo".to_string() };
+    execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg).unwrap();
+
+    let history = |deps: Deps, start_after| {
+        let msg = QueryMsg::ConfigHistory { start_after, limit: None };
+        let res: ConfigHistoryResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        res.versions
+    };
+    let versions = history(deps.as_ref(), None);
+    assert_eq!(4, versions.len());
+    // the instantiate settings come first
+    assert_eq!(versions[0].height, mock_env().block.height);
+    assert_eq!(
+        versions[1],
+        ConfigVersion {
+            version: 2,
+            change: ConfigChange::Config(Config {
+                default_timeout: 7200,
+                owner: Addr::unchecked("anyone"),
+            }),
+            changed_by: Addr::unchecked("anyone"),
+            height: env.block.height,
+        }
+    );
+    assert_eq!(
+        versions[3].change,
+        ConfigChange::Param {
+            key: "max_memo".to_string(),
+            value: None,
+        }
+    );
+    assert_eq!(2, history(deps.as_ref(), Some(2)).len());
+
+    // only the most recent versions are kept
+    for i in 0..100 {
+        let msg = ExecuteMsg::SetParam {
+            key: "max_memo".to_string(),
+            value: ParamValue::U64(i),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg).unwrap();
+    }
+    assert_eq!(history(deps.as_ref(), None)[0].version, 5);
+}
+
+#[test]
+fn migrate_gives_legacy_config_an_owner() {
+    let mut deps = setup(&[], &[]);
+    LEGACY_CONFIG.save(deps.as_mut().storage, &LegacyConfig { default_timeout: 7200 }).unwrap();
+
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg { owner: None }).unwrap_err();
+    assert_eq!(err, ContractError::MissingOwner {});
+    migrate(deps.as_mut(), mock_env(), MigrateMsg { owner: Some("admin".to_string()) }).unwrap();
+    let cfg = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(cfg, Config { default_timeout: 7200, owner: Addr::unchecked("admin") });
+
+    // a later migration keeps the owner unless it names a new one
+    migrate(deps.as_mut(), mock_env(), MigrateMsg { owner: None }).unwrap();
+    assert_eq!(CONFIG.load(deps.as_ref().storage).unwrap().owner, Addr::unchecked("admin"));
+    let msg = ExecuteMsg::UpdateConfig { default_timeout: Some(60), owner: None };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+}
+
+#[test]
+fn whitelist_query_reports_token_usage() {
+    let mut deps = setup(&["channel-3"], &["my-token", "idle-token"]);
+
+    let transfer = TransferMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    for (amount, seconds) in [(100u128, 0u64), (250, 60)] {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(seconds);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "my-account".into(),
+            amount: Uint128::new(amount),
+            msg: to_binary(&transfer).unwrap(),
+        });
+        execute(deps.as_mut(), env, mock_info("my-token", &[]), msg).unwrap();
+    }
+
+    let whitelisted = |contract: &str| {
+        let msg = QueryMsg::Whitelisted { contract: contract.to_string() };
+        let res: WhitelistResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        res
+    };
+    let res = whitelisted("my-token");
+    assert!(res.is_whitelist);
+    assert_eq!(
+        res.stats,
+        Some(TokenStats {
+            transfer_count: 2,
+            last_used: Some(mock_env().block.time.plus_seconds(60)),
+            volume: Uint128::new(350),
+        })
+    );
+    let res = whitelisted("idle-token");
+    assert!(res.is_whitelist);
+    assert_eq!(res.stats, None);
+}
+
+#[test]
+fn whitelisted_batch_answers_in_order() {
+    let deps = setup(&["channel-3"], &["my-token", "idle-token"]);
+
+    let msg = QueryMsg::WhitelistedBatch {
+        contracts: vec!["idle-token".to_string(), "unknown-token".to_string(), "my-token".to_string()],
+    };
+    let res: WhitelistedBatchResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let flags: Vec<_> = res.results.iter().map(|r| (r.contract.as_str(), r.is_whitelist)).collect();
+    assert_eq!(flags, vec![("idle-token", true), ("unknown-token", false), ("my-token", true)]);
+
+    // an invalid address is reported in its own row
+    let msg = QueryMsg::WhitelistedBatch {
+        contracts: vec!["x".to_string(), "my-token".to_string()],
+    };
+    let res: WhitelistedBatchResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert!(!res.results[0].is_whitelist);
+    assert!(res.results[0].error.is_some());
+    assert!(res.results[1].is_whitelist);
+    assert_eq!(res.results[1].error, None);
+
+    let msg = QueryMsg::WhitelistedBatch {
+        contracts: (0..31).map(|i| format!("token-{}", i)).collect(),
+    };
+    query(deps.as_ref(), mock_env(), msg).unwrap_err();
+}
+
+#[test]
+fn verifier_approves_unlisted_cw20_with_cache() {
+    let mut deps = setup(&["channel-3"], &["my-token"]);
+    let verifier_says = |approved: bool| {
+        move |msg: &Binary| {
+            let VerifierQueryMsg::IsApproved { contract } = from_binary(msg).unwrap();
+            ContractResult::Ok(to_binary(&ApprovalResponse { approved: approved && contract == "dao-token" }).unwrap())
+        }
+    };
+    deps.querier.update_smart("curation-dao", verifier_says(true));
+
+    let transfer = TransferMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    let send = |deps: &mut OwnedDeps<_, _, _>, token: &str, seconds: u64| {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(seconds);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "my-account".into(),
+            amount: Uint128::new(100),
+            msg: to_binary(&transfer).unwrap(),
+        });
+        execute(deps.as_mut(), env, mock_info(token, &[]), msg)
+    };
+
+    // without a verifier only the local whitelist counts
+    assert_eq!(send(&mut deps, "dao-token", 0).unwrap_err(), ContractError::NotOnAllowList);
+
+    let msg = ExecuteMsg::SetWhitelistVerifier {
+        verifier: Some(WhitelistVerifierMsg { address: "curation-dao".to_string(), cache_ttl: Some(600) }),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    // the queries agree with what can be sent
+    let allowed = |deps: &OwnedDeps<_, _, _>, contract: &str| {
+        let msg = QueryMsg::Whitelisted { contract: contract.to_string() };
+        let res: WhitelistResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        res.is_whitelist
+    };
+    assert!(allowed(&deps, "dao-token"));
+    assert!(!allowed(&deps, "spam-token"));
+    send(&mut deps, "my-token", 0).unwrap();
+    send(&mut deps, "dao-token", 0).unwrap();
+    assert_eq!(send(&mut deps, "spam-token", 0).unwrap_err(), ContractError::NotOnAllowList);
+
+    // the approval is trusted until the ttl runs out
+    deps.querier.update_smart("curation-dao", verifier_says(false));
+    send(&mut deps, "dao-token", 599).unwrap();
+    assert_eq!(send(&mut deps, "dao-token", 600).unwrap_err(), ContractError::NotOnAllowList);
+
+    // a transfer rejected after the verifier approved it leaves nothing in the cache
+    deps.querier.update_smart("curation-dao", verifier_says(true));
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(700);
+    let self_referral = TransferMsg { referrer: Some("my-account".to_string()), ..transfer.clone() };
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "my-account".into(),
+        amount: Uint128::new(100),
+        msg: to_binary(&self_referral).unwrap(),
+    });
+    let err = execute(deps.as_mut(), env, mock_info("dao-token", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::SelfReferral {});
+    deps.querier.update_smart("curation-dao", verifier_says(false));
+    assert_eq!(send(&mut deps, "dao-token", 700).unwrap_err(), ContractError::NotOnAllowList);
+
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::WhitelistVerifier {}).unwrap();
+    let res: WhitelistVerifierResponse = from_binary(&raw).unwrap();
+    assert_eq!(res.verifier.unwrap().cache_ttl, Some(600));
+}
+
+#[test]
+fn fee_on_transfer_cw20_bridges_credited_amount() {
+    let mut deps = setup(&["channel-3"], &["tax-token"]);
+    let balance = Rc::new(Cell::new(1_000u128));
+    let held = balance.clone();
+    deps.querier.update_smart("tax-token", move |msg| match from_binary(msg).unwrap() {
+        Cw20QueryMsg::Balance { address } => {
+            assert_eq!(address, MOCK_CONTRACT_ADDR);
+            ContractResult::Ok(to_binary(&BalanceResponse { balance: Uint128::new(held.get()) }).unwrap())
+        }
+        _ => ContractResult::Err("unexpected query".to_string()),
+    });
+    let msg = ExecuteMsg::SetFeeOnTransfer { contract: "tax-token".to_string(), enabled: true };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    let transfer = TransferMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+        timeout_duration: None,
+    };
+    // a plain send would credit escrow with the untaxed amount
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "my-account".into(),
+        amount: Uint128::new(100),
+        msg: to_binary(&transfer).unwrap(),
+    });
+    let err = execute(deps.as_mut(), mock_env(), mock_info("tax-token", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::FeeOnTransferToken { contract: "tax-token".to_string() });
+
+    // attached coins would be stranded, and there is no way to attach a native fee
+    let msg = ExecuteMsg::TransferCw20 { contract: "tax-token".to_string(), amount: Uint128::new(100), msg: transfer.clone() };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("my-account", &coins(5, "ucosm")), msg).unwrap_err();
+    assert_eq!(err, ContractError::Payment(PaymentError::NonPayable {}));
+    let msg = ExecuteMsg::SetFeeConfig {
+        fee_config: Some(FeeConfigMsg {
+            bps: 0,
+            collector: "treasury".to_string(),
+            fee_denom: Some(FlatFee { denom: "ufee".to_string(), amount: Uint128::new(50) }),
+            referrer_bps: 0,
+            collectors: vec![],
+        }),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    let msg = ExecuteMsg::TransferCw20 {
+        contract: "tax-token".to_string(),
+        amount: Uint128::new(100),
+        msg: TransferMsg { fee_in_fee_denom: true, ..transfer.clone() },
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("my-account", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::FeeNotAttachable { denom: "ufee".to_string() });
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), ExecuteMsg::SetFeeConfig { fee_config: None }).unwrap();
+
+    let msg = ExecuteMsg::TransferCw20 { contract: "tax-token".to_string(), amount: Uint128::new(100), msg: transfer };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("my-account", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "tax-token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: "my-account".to_string(),
+                recipient: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::new(100),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+
+    // the token keeps 2% of the pull
+    balance.set(1_098);
+    let reply_msg = Reply {
+        id: res.messages[0].id,
+        result: ContractResult::Ok(SubMsgExecutionResponse { events: vec![], data: None }),
+    };
+    let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+    assert_eq!(sent_packet(&res).amount, Uint128::new(98));
+
+    let raw = query(deps.as_ref(), mock_env(), QueryMsg::Channel { id: "channel-3".to_string() }).unwrap();
+    let chan: ChannelResponse = from_binary(&raw).unwrap();
+    assert_eq!(chan.balances, vec![Amount::from_parts("cw20:tax-token".to_string(), Uint128::new(98))]);
+}
+
+#[test]
+fn timeout_accepts_readable_durations() {
+    let mut deps = setup(&["channel-3"], &[]);
+
+    let transfer = |timeout: Option<u64>, duration: Option<&str>| TransferMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout,
+        timeout_duration: duration.map(String::from),
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: None,
+        referrer: None,
+        route: None,
+    };
+    let mut send = |msg: TransferMsg| {
+        let info = mock_info("foobar", &coins(1000, "ucosm"));
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(msg))
+    };
+    let timeout_of = |res: &Response| match &res.messages[0].msg {
+        CosmosMsg::Ibc(IbcMsg::SendPacket { timeout, .. }) => timeout.timestamp().unwrap(),
+        _ => panic!("no packet sent"),
+    };
+
+    let res = send(transfer(None, Some("1h30m"))).unwrap();
+    assert_eq!(timeout_of(&res), mock_env().block.time.plus_seconds(5400));
+    let res = send(transfer(None, Some("90s"))).unwrap();
+    assert_eq!(timeout_of(&res), mock_env().block.time.plus_seconds(90));
+
+    for bad in ["1500ms", "90", "", "2 h", "h"] {
+        let err = send(transfer(None, Some(bad))).unwrap_err();
+        assert_eq!(err, ContractError::InvalidDuration { duration: bad.to_string() });
+    }
+    // an hour given in milliseconds
+    let err = send(transfer(Some(3_600_000), None)).unwrap_err();
+    assert_eq!(err, ContractError::TimeoutTooLong { timeout: 3_600_000, max: 30 * 24 * 60 * 60 });
+    let err = send(transfer(Some(60), Some("1m"))).unwrap_err();
+    assert_eq!(err, ContractError::ConflictingTimeouts {});
+
+    // the default timeout is capped like a requested one
+    let msg = ExecuteMsg::SetParam { key: "max_timeout".to_string(), value: ParamValue::U64(600) };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    let info = mock_info("foobar", &coins(1000, "ucosm"));
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer(None, None))).unwrap();
+    assert_eq!(timeout_of(&res), mock_env().block.time.plus_seconds(600));
+}
+
+#[test]
+fn memo_and_packet_size_are_limited() {
+    let mut deps = setup(&["channel-3"], &[]);
+    let owner = mock_info("anyone", &[]);
+
+    let transfer = |memo: &str| TransferMsg {
+        channel: "channel-3".to_string(),
+        remote_address: "foreign-address".to_string(),
+        timeout: None,
+        timeout_duration: None,
+        denom: None,
+        fee_in_fee_denom: false,
+        memo: Some(memo.to_string()),
+        referrer: None,
+        route: None,
+    };
+    let mut send = |memo: &str| {
+        let info = mock_info("foobar", &coins(1000, "ucosm"));
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer(memo)))
+    };
+
+    // ibc-go's memo limit applies by default
+    send(&"x".repeat(32_768)).unwrap();
+    let err = send(&"x".repeat(32_769)).unwrap_err();
+    assert_eq!(err, ContractError::MemoTooLong { length: 32_769, max: 32_768 });
+
+    let msg = ExecuteMsg::SetParam { key: "max_memo_length".to_string(), value: ParamValue::U64(64) };
+    execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap();
+    let msg = ExecuteMsg::SetParam { key: "max_packet_size".to_string(), value: ParamValue::U64(150) };
+    execute(deps.as_mut(), mock_env(), owner, msg).unwrap();
+
+    let mut send = |memo: &str| {
+        let info = mock_info("foobar", &coins(1000, "ucosm"));
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Transfer(transfer(memo)))
+    };
+    let err = send(&"x".repeat(65)).unwrap_err();
+    assert_eq!(err, ContractError::MemoTooLong { length: 65, max: 64 });
+    send("short").unwrap();
+    let err = send(&"x".repeat(64)).unwrap_err();
+    assert!(matches!(err, ContractError::PacketTooLarge { max: 150, .. }));
+}
@@ -0,0 +1,38 @@
+use crate::error::ContractError;
+use crate::msg::{ApprovalResponse, VerifierQueryMsg};
+use crate::state::{VerifierApproval, VERIFIER_APPROVALS, WHITELIST_VERIFIER, WHITE_LIST};
+use cosmwasm_std::{Addr, Deps, Timestamp};
+
+/// Checks a cw20 may be sent: it is on the local whitelist, has a cached approval from the
+/// current verifier, or the verifier approves it now. A fresh approval is returned when the
+/// verifier asks for it to be cached. The caller saves it in VERIFIER_APPROVALS once nothing else
+/// can reject the transfer.
+pub fn check_cw20(
+    deps: Deps,
+    now: Timestamp,
+    token: &Addr,
+) -> Result<Option<VerifierApproval>, ContractError> {
+    if WHITE_LIST.has(deps.storage, token) {
+        return Ok(None);
+    }
+    let verifier = WHITELIST_VERIFIER
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NotOnAllowList)?;
+    if let Some(cached) = VERIFIER_APPROVALS.may_load(deps.storage, token)? {
+        if cached.verifier == verifier.address && now < cached.expires {
+            return Ok(None);
+        }
+    }
+
+    let query = VerifierQueryMsg::IsApproved {
+        contract: token.to_string(),
+    };
+    let res: ApprovalResponse = deps.querier.query_wasm_smart(&verifier.address, &query)?;
+    if !res.approved {
+        return Err(ContractError::NotOnAllowList);
+    }
+    Ok(verifier.cache_ttl.map(|ttl| VerifierApproval {
+        verifier: verifier.address,
+        expires: now.plus_seconds(ttl),
+    }))
+}